@@ -0,0 +1,138 @@
+use cosmwasm_std::{Addr, DepsMut, Env, Isqrt, MessageInfo, Response, StdError, StdResult, Uint128};
+
+use crate::execute::lock::settle_rewards;
+use crate::execute::{accrue_rewards, calculate_amm_swap, ensure_pool_active};
+use crate::execute::liquidity::{calculate_deposit, stake_shares};
+use crate::state::{CONFIG, POOL_INFO, USER_INFO, AUTO_COMPOUND, load_contracts};
+
+
+// ERTH to sell so the rest pairs exactly with what the sale returns:
+// sqrt(r * (r + amount)) - r for an ERTH reserve r, with no fee taken
+fn zap_swap_amount(erth_reserve: Uint128, amount: Uint128) -> StdResult<Uint128> {
+    let product = erth_reserve.full_mul(erth_reserve + amount);
+    let root = Uint128::try_from(product.isqrt())?;
+    Ok(root.saturating_sub(erth_reserve).min(amount))
+}
+
+// -------------------------
+// Compound rewards back into staked liquidity
+// -------------------------
+pub fn compound_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pools: Vec<String>,
+) -> StdResult<Response> {
+    compound(deps, env, info.sender, pools, "compound_rewards")
+}
+
+pub fn compound_rewards_for(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    user: String,
+    pools: Vec<String>,
+) -> StdResult<Response> {
+    let user = deps.api.addr_validate(&user)?;
+    match AUTO_COMPOUND.get(deps.storage, &user) {
+        Some(keeper) if keeper == info.sender => {}
+        Some(_) => return Err(StdError::generic_err("Only the user's auto compound keeper can compound for them")),
+        None => return Err(StdError::generic_err("User has not opted in to auto compounding")),
+    }
+    compound(deps, env, user, pools, "auto_compound_rewards")
+}
+
+pub fn set_auto_compound(
+    deps: DepsMut,
+    info: MessageInfo,
+    keeper: Option<String>,
+) -> StdResult<Response> {
+    let keeper = keeper.map(|keeper| deps.api.addr_validate(&keeper)).transpose()?;
+    match &keeper {
+        Some(keeper) => AUTO_COMPOUND.insert(deps.storage, &info.sender, keeper)?,
+        None => AUTO_COMPOUND.remove(deps.storage, &info.sender)?,
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_auto_compound")
+        .add_attribute("user", info.sender)
+        .add_attribute("keeper", keeper.map(|k| k.to_string()).unwrap_or_default()))
+}
+
+// The rewards are already held by the contract, so compounding only moves
+// them into the pool's reserves and no tokens are sent anywhere. The zap is
+// fee free and is not counted as volume.
+fn compound(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    pools: Vec<String>,
+    action: &str,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    // Compounded shares can be withdrawn right away, which would skip vesting
+    if config.reward_vesting_seconds > 0 {
+        return Err(StdError::generic_err("Compounding is disabled while rewards vest"));
+    }
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let erth_addr = addrs.erth_token.address;
+
+    let mut total_compounded = Uint128::zero();
+    let mut total_shares = Uint128::zero();
+
+    for pool in pools.iter() {
+        let pool_addr = deps.api.addr_validate(pool)?;
+        let mut pool_info = POOL_INFO
+            .get(deps.storage, &pool_addr)
+            .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+        ensure_pool_active(&pool_info)?;
+        accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+        let mut user_info = user_info_by_pool
+            .get(deps.storage, &user)
+            .ok_or_else(|| StdError::generic_err("User info not found"))?;
+        settle_rewards(&mut pool_info, &mut user_info, env.block.time.seconds())?;
+
+        let rewards = user_info.pending_rewards;
+        if rewards.is_zero() {
+            continue;
+        }
+
+        // Sell part of the rewards for token B inside the pool
+        let swap_amount = zap_swap_amount(pool_info.state.erth_reserve, rewards)?;
+        let amount_b = calculate_amm_swap(
+            &erth_addr, config.protocol_fee, &pool_info, &erth_addr, swap_amount, false,
+        )?.output_amount;
+        let mut zapped = pool_info.state.clone();
+        zapped.erth_reserve += swap_amount;
+        zapped.token_b_reserve = zapped.token_b_reserve.checked_sub(amount_b)?;
+
+        // Deposit the remainder with the token B bought. Token B the deposit
+        // doesn't use stays in the reserves, unused ERTH stays pending.
+        // Rewards too small to mint a share are left pending as well.
+        let deposit = match calculate_deposit(&zapped, rewards - swap_amount, amount_b) {
+            Ok(deposit) if !deposit.shares.is_zero() => deposit,
+            _ => continue,
+        };
+        pool_info.state = zapped;
+        pool_info.state.erth_reserve += deposit.amount_erth;
+        pool_info.state.token_b_reserve += amount_b;
+        pool_info.state.total_shares += deposit.shares;
+
+        let compounded = swap_amount + deposit.amount_erth;
+        user_info.pending_rewards = rewards - compounded;
+        user_info_by_pool.insert(deps.storage, &user, &user_info)?;
+        stake_shares(deps.storage, &pool_addr, &mut pool_info, &user, deposit.shares, env.block.time.seconds())?;
+        POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+        total_compounded += compounded;
+        total_shares += deposit.shares;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", action)
+        .add_attribute("user", user)
+        .add_attribute("erth_compounded", total_compounded.to_string())
+        .add_attribute("shares_added", total_shares.to_string()))
+}
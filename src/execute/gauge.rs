@@ -0,0 +1,214 @@
+use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Response, StdError, StdResult, Storage, Uint128};
+use secret_toolkit_storage::Keymap;
+
+use crate::msg::PoolVote;
+use crate::state::{CONFIG, POOL_INFO, GAUGE_VOTES, GAUGE_EPOCH_VOTES, GAUGE_WEIGHTS,
+    GAUGE_VERIFIED_WEIGHTS, GAUGE_FINALIZED, POOL_MIGRATION, GaugeVote, GaugeVoteRecord, GaugeWeight, load_contracts, query_staked_amount};
+
+
+// Adds (or removes) a vote's share of power to the tally
+fn apply_votes(weights: &mut Vec<GaugeWeight>, record: &GaugeVoteRecord, add: bool) -> StdResult<()> {
+    for vote in record.votes.iter() {
+        let amount = record.power.multiply_ratio(vote.weight, Uint128::from(10000u32));
+        match weights.iter_mut().find(|w| w.pool == vote.pool) {
+            Some(entry) if add => entry.votes += amount,
+            Some(entry) => entry.votes = entry.votes.checked_sub(amount)?,
+            None if add => weights.push(GaugeWeight { pool: vote.pool.clone(), votes: amount }),
+            None => return Err(StdError::generic_err("Gauge tally is missing a previous vote")),
+        }
+    }
+    weights.retain(|w| !w.votes.is_zero());
+    Ok(())
+}
+
+// -------------------------
+// Vote on how next epoch's emissions are split between pools
+// -------------------------
+pub fn vote_gauges(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    votes: Vec<PoolVote>,
+) -> StdResult<Response> {
+    ensure_no_pool_migration(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let epoch = env.block.time.seconds() / config.gauge_epoch_seconds;
+    let target_epoch = epoch + 1;
+
+    // Voting power is the voter's ERTH stake in the staking contract
+    let power = query_staked_amount(&deps.as_ref(), &addrs.staking, &info.sender)?;
+    if power.is_zero() {
+        return Err(StdError::generic_err("No staked ERTH to vote with"));
+    }
+
+    if votes.is_empty() {
+        return Err(StdError::generic_err("No votes given"));
+    }
+    let mut gauge_votes: Vec<GaugeVote> = vec![];
+    let mut total_weight = Uint128::zero();
+    for vote in votes {
+        let pool_addr = deps.api.addr_validate(&vote.pool)?;
+        let pool_info = POOL_INFO
+            .get(deps.storage, &pool_addr)
+            .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+        if pool_info.state.deprecated {
+            return Err(StdError::generic_err("Cannot vote for a deprecated pool"));
+        }
+        if vote.weight.is_zero() {
+            return Err(StdError::generic_err("Vote weight must be greater than zero"));
+        }
+        if gauge_votes.iter().any(|v| v.pool == pool_addr) {
+            return Err(StdError::generic_err("Duplicate pool in votes"));
+        }
+        total_weight += vote.weight;
+        gauge_votes.push(GaugeVote {
+            pool: pool_addr,
+            weight: vote.weight,
+        });
+    }
+    if total_weight > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("Vote weights cannot exceed 10000 basis points"));
+    }
+
+    // A second vote in the same epoch replaces the first
+    let mut weights = GAUGE_WEIGHTS.get(deps.storage, &target_epoch).unwrap_or_default();
+    let epoch_votes = GAUGE_EPOCH_VOTES.add_suffix(&target_epoch.to_be_bytes());
+    if let Some(previous) = epoch_votes.get(deps.storage, &info.sender) {
+        apply_votes(&mut weights, &previous, false)?;
+    }
+
+    let record = GaugeVoteRecord {
+        epoch,
+        power,
+        votes: gauge_votes,
+    };
+    apply_votes(&mut weights, &record, true)?;
+
+    GAUGE_WEIGHTS.insert(deps.storage, &target_epoch, &weights)?;
+    epoch_votes.insert(deps.storage, &info.sender, &record)?;
+    GAUGE_VOTES.insert(deps.storage, &info.sender, &record)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "vote_gauges")
+        .add_attribute("voter", info.sender)
+        .add_attribute("power", power.to_string())
+        .add_attribute("epoch", target_epoch.to_string()))
+}
+
+// Voters re-checked per FinalizeGaugeEpoch call
+const DEFAULT_FINALIZE_LIMIT: u32 = 50;
+const MAX_FINALIZE_LIMIT: u32 = 200;
+
+// -------------------------
+// Finalize an epoch's gauge tally, a page of voters per call. Each vote counts
+// with the lower of its recorded power and the voter's stake now, so ERTH moved
+// to another address after voting can't be counted twice. Votes only steer
+// emissions once their epoch is finalized. Anyone can call this, including for
+// past epochs, which clears their leftover vote records.
+// -------------------------
+pub fn finalize_gauge_epoch(
+    deps: DepsMut,
+    env: Env,
+    epoch: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    ensure_no_pool_migration(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let current_epoch = env.block.time.seconds() / config.gauge_epoch_seconds;
+    let epoch = epoch.unwrap_or(current_epoch);
+    if epoch > current_epoch {
+        return Err(StdError::generic_err("Epoch has not started yet"));
+    }
+    if GAUGE_FINALIZED.contains(deps.storage, &epoch) {
+        return Err(StdError::generic_err("Epoch is already finalized"));
+    }
+    let limit = limit.unwrap_or(DEFAULT_FINALIZE_LIMIT).min(MAX_FINALIZE_LIMIT) as usize;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+
+    let epoch_votes = GAUGE_EPOCH_VOTES.add_suffix(&epoch.to_be_bytes());
+    let mut records = vec![];
+    for item in epoch_votes.iter(deps.storage)?.take(limit) {
+        records.push(item?);
+    }
+    let mut weights = GAUGE_VERIFIED_WEIGHTS.get(deps.storage, &epoch).unwrap_or_default();
+    let counted = records.len();
+    for (voter, mut record) in records {
+        // A stake that can't be read counts as none rather than halting the page
+        let staked = query_staked_amount(&deps.as_ref(), &addrs.staking, &voter).unwrap_or_default();
+        record.power = record.power.min(staked);
+        apply_votes(&mut weights, &record, true)?;
+        epoch_votes.remove(deps.storage, &voter)?;
+    }
+
+    let finalized = epoch_votes.get_len(deps.storage)? == 0;
+    if finalized {
+        GAUGE_WEIGHTS.insert(deps.storage, &epoch, &weights)?;
+        GAUGE_VERIFIED_WEIGHTS.remove(deps.storage, &epoch)?;
+        GAUGE_FINALIZED.insert(deps.storage, &epoch, &true)?;
+    } else {
+        GAUGE_VERIFIED_WEIGHTS.insert(deps.storage, &epoch, &weights)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "finalize_gauge_epoch")
+        .add_attribute("epoch", epoch.to_string())
+        .add_attribute("voters_counted", counted.to_string())
+        .add_attribute("finalized", finalized.to_string()))
+}
+
+fn rename_pool(record: &mut GaugeVoteRecord, old_pool: &Addr, new_pool: &Addr) -> bool {
+    let mut renamed = false;
+    for vote in record.votes.iter_mut().filter(|vote| vote.pool == *old_pool) {
+        vote.pool = new_pool.clone();
+        renamed = true;
+    }
+    renamed
+}
+
+// Points a page of vote records at a pool's new address, starting at `cursor`.
+// Returns how many records were read.
+pub fn migrate_gauge_votes(
+    storage: &mut dyn Storage,
+    records: &Keymap<Addr, GaugeVoteRecord>,
+    old_pool: &Addr,
+    new_pool: &Addr,
+    cursor: u32,
+    limit: usize,
+) -> StdResult<usize> {
+    let mut page = vec![];
+    for item in records.iter(storage)?.skip(cursor as usize).take(limit) {
+        page.push(item?);
+    }
+    let read = page.len();
+    for (voter, mut record) in page {
+        if rename_pool(&mut record, old_pool, new_pool) {
+            records.insert(storage, &voter, &record)?;
+        }
+    }
+    Ok(read)
+}
+
+// Points the tallies for `epoch` and the one after at a pool's new address
+pub fn migrate_gauge_weights(storage: &mut dyn Storage, old_pool: &Addr, new_pool: &Addr, epoch: u64) -> StdResult<()> {
+    for epoch in [epoch, epoch + 1] {
+        for tallies in [&GAUGE_WEIGHTS, &GAUGE_VERIFIED_WEIGHTS] {
+            if let Some(mut weights) = tallies.get(storage, &epoch) {
+                for weight in weights.iter_mut().filter(|weight| weight.pool == *old_pool) {
+                    weight.pool = new_pool.clone();
+                }
+                tallies.insert(storage, &epoch, &weights)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Vote records are renamed in pages during a pool migration, so they must not
+// change until it finishes
+fn ensure_no_pool_migration(storage: &dyn Storage) -> StdResult<()> {
+    if POOL_MIGRATION.may_load(storage)?.is_some() {
+        return Err(StdError::generic_err("Gauge votes are frozen while a pool migration runs"));
+    }
+    Ok(())
+}
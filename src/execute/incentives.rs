@@ -0,0 +1,312 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Storage, Uint128, Uint256, WasmMsg};
+use secret_toolkit::snip20;
+
+use crate::execute::{reward_shares, unscale, accrue_rewards, ensure_pool_active, SCALING_FACTOR};
+use crate::execute::lock::settle_rewards;
+use crate::state::{CONFIG, POOL_INFO, USER_INFO, INCENTIVE_TOKENS, PoolInfo, UserInfo, UserIncentive, PoolIncentive,
+    IncentiveStream, ContractInfo};
+
+// Accumulators are never dropped since user debts refer to them, so both
+// lists are capped to keep pool updates cheap
+pub const MAX_INCENTIVE_TOKENS: usize = 8;
+pub const MAX_INCENTIVE_STREAMS: usize = 16;
+
+
+// Moves every incentive accumulator up to `now`. While a pool has no shares
+// the emission is set aside in `undistributed` instead.
+pub fn accrue_incentives(pool_info: &mut PoolInfo, now: u64) {
+    let divisor = reward_shares(&pool_info.state);
+
+    for incentive in pool_info.state.incentives.iter_mut() {
+        if now <= incentive.last_update {
+            continue;
+        }
+        let mut emitted = Uint128::zero();
+        for stream in incentive.streams.iter() {
+            let from = incentive.last_update.max(stream.start_time);
+            let to = now.min(stream.end_time);
+            if to > from {
+                emitted += stream.amount.multiply_ratio(to - from, stream.end_time - stream.start_time);
+            }
+        }
+        if divisor.is_zero() {
+            incentive.undistributed += emitted;
+        } else {
+            incentive.reward_per_token_scaled += Uint256::from(emitted) * SCALING_FACTOR / Uint256::from(divisor);
+        }
+        incentive.last_update = now;
+        let last_update = incentive.last_update;
+        incentive.streams.retain(|stream| stream.end_time > last_update);
+    }
+}
+
+fn incentive_debt(incentive: &PoolIncentive, shares: Uint128) -> Uint256 {
+    Uint256::from(shares) * incentive.reward_per_token_scaled
+}
+
+// Moves what each incentive accrued for `shares` into the user's pending amounts
+pub fn settle_user_incentives(pool_info: &PoolInfo, user_info: &mut UserInfo, shares: Uint128) -> StdResult<()> {
+    for incentive in pool_info.state.incentives.iter() {
+        let debt = incentive_debt(incentive, shares);
+        match user_info.incentives.iter_mut().find(|i| i.token == incentive.token.address) {
+            Some(entry) => {
+                entry.pending_rewards += unscale(debt.checked_sub(entry.reward_debt)?)?;
+                entry.reward_debt = debt;
+            }
+            // Held these shares since before the token was added, when its accumulator was zero
+            None => user_info.incentives.push(UserIncentive {
+                token: incentive.token.address.clone(),
+                reward_debt: debt,
+                pending_rewards: unscale(debt)?,
+            }),
+        }
+    }
+    Ok(())
+}
+
+pub fn reset_incentive_debts(pool_info: &PoolInfo, user_info: &mut UserInfo, shares: Uint128) {
+    for incentive in pool_info.state.incentives.iter() {
+        let debt = incentive_debt(incentive, shares);
+        match user_info.incentives.iter_mut().find(|i| i.token == incentive.token.address) {
+            Some(entry) => entry.reward_debt = debt,
+            None => user_info.incentives.push(UserIncentive {
+                token: incentive.token.address.clone(),
+                reward_debt: debt,
+                pending_rewards: Uint128::zero(),
+            }),
+        }
+    }
+}
+
+pub fn has_pending_incentives(user_info: &UserInfo) -> bool {
+    user_info.incentives.iter().any(|entry| !entry.pending_rewards.is_zero())
+}
+
+// Takes the user's pending incentives as (token, amount) pairs, limited to
+// `tokens` when given
+pub fn take_pending_incentives(
+    storage: &dyn Storage,
+    user_info: &mut UserInfo,
+    tokens: Option<&[Addr]>,
+) -> Vec<(ContractInfo, Uint128)> {
+    let mut payouts = vec![];
+    for entry in user_info.incentives.iter_mut() {
+        if entry.pending_rewards.is_zero() {
+            continue;
+        }
+        if tokens.is_some_and(|tokens| !tokens.contains(&entry.token)) {
+            continue;
+        }
+        if let Some(code_hash) = INCENTIVE_TOKENS.get(storage, &entry.token) {
+            payouts.push((ContractInfo { address: entry.token.clone(), code_hash }, entry.pending_rewards));
+            entry.pending_rewards = Uint128::zero();
+        }
+    }
+    payouts
+}
+
+// One transfer per token, merging amounts from several pools
+pub fn incentive_payout_messages(
+    recipient: &Addr,
+    payouts: Vec<(ContractInfo, Uint128)>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut totals: Vec<(ContractInfo, Uint128)> = vec![];
+    for (token, amount) in payouts {
+        match totals.iter_mut().find(|(t, _)| t.address == token.address) {
+            Some((_, total)) => *total += amount,
+            None => totals.push((token, amount)),
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(token, amount)| {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: token.address.to_string(),
+                code_hash: token.code_hash,
+                msg: to_binary(&snip20::HandleMsg::Transfer {
+                    recipient: recipient.to_string(),
+                    amount,
+                    padding: None,
+                    memo: None,
+                })?,
+                funds: vec![],
+            }))
+        })
+        .collect()
+}
+
+// -------------------------
+// Claim incentive tokens from one pool without claiming ERTH. ClaimRewards
+// pays them out too; withdrawals leave them for either claim.
+// -------------------------
+pub fn claim_incentives(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    tokens: Option<Vec<String>>,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let tokens = tokens
+        .map(|tokens| tokens.iter().map(|token| deps.api.addr_validate(token)).collect::<StdResult<Vec<_>>>())
+        .transpose()?;
+    let now = env.block.time.seconds();
+
+    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+    let mut user_info = user_info_by_pool
+        .get(deps.storage, &info.sender)
+        .ok_or_else(|| StdError::generic_err("User info not found"))?;
+
+    // A removed pool leaves only what was already settled
+    if let Some(mut pool_info) = POOL_INFO.get(deps.storage, &pool_addr) {
+        accrue_rewards(&mut pool_info, now)?;
+        settle_rewards(&mut pool_info, &mut user_info, now)?;
+        POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+    }
+
+    let payouts = take_pending_incentives(deps.storage, &mut user_info, tokens.as_deref());
+    if payouts.is_empty() {
+        return Err(StdError::generic_err("No incentives to claim"));
+    }
+
+    if user_info.amount_staked.is_zero() && user_info.pending_rewards.is_zero() && !has_pending_incentives(&user_info) {
+        user_info_by_pool.remove(deps.storage, &info.sender)?;
+    } else {
+        user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
+    }
+
+    Ok(Response::new()
+        .add_messages(incentive_payout_messages(&info.sender, payouts)?)
+        .add_attribute("action", "claim_incentives")
+        .add_attribute("user", info.sender)
+        .add_attribute("pool", pool_addr))
+}
+
+// -------------------------
+// Register a SNIP-20 so it can be deposited as an incentive. Every claim pays
+// out each token a position holds, so only the manager may vouch for one.
+// -------------------------
+pub fn register_incentive_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: String,
+    hash: String,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let token_addr = deps.api.addr_validate(&token)?;
+    if INCENTIVE_TOKENS.contains(deps.storage, &token_addr) {
+        return Err(StdError::generic_err("Incentive token already registered"));
+    }
+    INCENTIVE_TOKENS.insert(deps.storage, &token_addr, &hash)?;
+
+    let register_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: token_addr.to_string(),
+        code_hash: hash,
+        msg: to_binary(&snip20::HandleMsg::RegisterReceive {
+            code_hash: env.contract.code_hash,
+            padding: None,
+        })?,
+        funds: vec![],
+    });
+
+    Ok(Response::new()
+        .add_message(register_msg)
+        .add_attribute("action", "register_incentive_token")
+        .add_attribute("token", token_addr))
+}
+
+// -------------------------
+// Add an incentive stream to a pool (sent in through Receive)
+// -------------------------
+#[allow(clippy::too_many_arguments)]
+pub fn add_incentive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: Addr,
+    amount: Uint128,
+    pool: String,
+    start_time: Option<u64>,
+    end_time: u64,
+) -> StdResult<Response> {
+    let token_hash = INCENTIVE_TOKENS
+        .get(deps.storage, &info.sender)
+        .ok_or_else(|| StdError::generic_err("Incentive token not registered"))?;
+
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+
+    let now = env.block.time.seconds();
+    let start_time = start_time.unwrap_or(now);
+    if amount.is_zero() {
+        return Err(StdError::generic_err("amount must be greater than zero"));
+    }
+    if start_time < now {
+        return Err(StdError::generic_err("Incentive cannot start in the past"));
+    }
+    if end_time <= start_time {
+        return Err(StdError::generic_err("Incentive must end after it starts"));
+    }
+
+    accrue_incentives(&mut pool_info, now);
+
+    let position = pool_info
+        .state
+        .incentives
+        .iter()
+        .position(|i| i.token.address == info.sender);
+    let index = match position {
+        Some(index) => index,
+        None => {
+            if pool_info.state.incentives.len() >= MAX_INCENTIVE_TOKENS {
+                return Err(StdError::generic_err("Pool has too many incentive tokens"));
+            }
+            pool_info.state.incentives.push(PoolIncentive {
+                token: ContractInfo {
+                    address: info.sender.clone(),
+                    code_hash: token_hash,
+                },
+                reward_per_token_scaled: Uint256::zero(),
+                last_update: now,
+                streams: vec![],
+                undistributed: Uint128::zero(),
+            });
+            pool_info.state.incentives.len() - 1
+        }
+    };
+
+    let incentive = &mut pool_info.state.incentives[index];
+    if incentive.streams.len() >= MAX_INCENTIVE_STREAMS {
+        return Err(StdError::generic_err("Too many running incentives for this token"));
+    }
+    // What went unpaid while the pool had no stakers rides along
+    let recycled = incentive.undistributed;
+    incentive.undistributed = Uint128::zero();
+    incentive.streams.push(IncentiveStream {
+        amount: amount + recycled,
+        start_time,
+        end_time,
+    });
+
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "add_incentive")
+        .add_attribute("from", from)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("token", info.sender)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("recycled", recycled.to_string())
+        .add_attribute("start_time", start_time.to_string())
+        .add_attribute("end_time", end_time.to_string()))
+}
@@ -1,14 +1,20 @@
 use cosmwasm_std::{
-    to_binary, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128, WasmMsg,
+    to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError,
+    StdResult, Storage, Uint128, Uint256, WasmMsg,
 };
 use secret_toolkit::{snip20,};
 
 use crate::{
-    execute::{update_user_rewards, SCALING_FACTOR},
+    msg::UnbondClaimResult,
+    execute::{reset_reward_debt, accrue_rewards, calculate_amm_swap, ensure_pool_active},
+    execute::incentives::has_pending_incentives,
+    execute::lock::{locked_amount, settle_rewards},
+    execute::rewards::erth_transfer_msg,
+    execute::vesting::vest_rewards,
     state::{
         CONFIG, STATE, POOL_INFO, USER_INFO, UnbondRecord, UNBONDING_REQUESTS,
-        load_contracts,
+        AUTO_CLAIM_UNBOND, Config, PoolInfo, PoolState, ContractInfo, LateClaimPolicy, load_contracts,
+        track_user_pool, prune_user_pools, USER_POOLS,
     },
 };
 
@@ -27,6 +33,66 @@ fn sqrt_u128(n: u128) -> u128 {
     x
 }
 
+// Shares locked forever on a pool's first deposit so the share price can't be
+// inflated from a dust-sized supply
+pub const MINIMUM_LIQUIDITY: Uint128 = Uint128::new(1_000);
+
+// a * b / c, rounded up
+fn mul_div_ceil(a: Uint128, b: Uint128, c: Uint128) -> StdResult<Uint128> {
+    let c = Uint256::from(c);
+    let result = (a.full_mul(b) + c - Uint256::from(1u8)) / c;
+    Ok(Uint128::try_from(result)?)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Deposit {
+    pub shares: Uint128,
+    pub locked_shares: Uint128,
+    pub amount_erth: Uint128,
+    pub amount_b: Uint128,
+}
+
+// Shares minted for a deposit and the amounts actually taken. Shares round down
+// and the amounts taken round up, so rounding always favors the pool.
+pub fn calculate_deposit(
+    state: &PoolState,
+    amount_erth: Uint128,
+    amount_b: Uint128,
+) -> StdResult<Deposit> {
+    if state.total_shares.is_zero() {
+        // Use square root of product for initial shares
+        let product = amount_erth
+            .checked_mul(amount_b)?;
+        let initial_shares = Uint128::from(sqrt_u128(product.u128()));
+        if initial_shares <= MINIMUM_LIQUIDITY {
+            return Err(StdError::generic_err("Initial liquidity too small"));
+        }
+        return Ok(Deposit {
+            shares: initial_shares - MINIMUM_LIQUIDITY,
+            locked_shares: MINIMUM_LIQUIDITY,
+            amount_erth,
+            amount_b,
+        });
+    }
+
+    if state.erth_reserve.is_zero() || state.token_b_reserve.is_zero() {
+        return Err(StdError::generic_err("Pool has zero reserves"));
+    }
+    let share_erth = amount_erth.multiply_ratio(state.total_shares, state.erth_reserve);
+    let share_b = amount_b.multiply_ratio(state.total_shares, state.token_b_reserve);
+    let shares = share_erth.min(share_b);
+    if shares.is_zero() {
+        return Err(StdError::generic_err("Deposit too small to mint any shares"));
+    }
+
+    Ok(Deposit {
+        shares,
+        locked_shares: Uint128::zero(),
+        amount_erth: mul_div_ceil(shares, state.erth_reserve, state.total_shares)?,
+        amount_b: mul_div_ceil(shares, state.token_b_reserve, state.total_shares)?,
+    })
+}
+
 // -------------------------
 // Add Liquidity
 // -------------------------
@@ -37,6 +103,7 @@ pub fn add_liquidity(
     amount_erth: Uint128,
     amount_b: Uint128,
     pool: String,
+    stake: bool,
 ) -> Result<Response, StdError> {
     let config = CONFIG.load(deps.storage)?;
     let addrs = load_contracts(&deps.as_ref(), &config)?;
@@ -46,26 +113,24 @@ pub fn add_liquidity(
     let mut pool_info = POOL_INFO
         .get(deps.storage, &pool_addr)
         .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
 
     // Determine LP shares
-    let (shares, adjusted_amount_erth, adjusted_amount_b) =
-        if pool_info.state.total_shares.is_zero() {
-            // Use square root of product for initial shares
-            let product = amount_erth
-                .checked_mul(amount_b)?;
-            let shares = Uint128::from(sqrt_u128(product.u128()));
-            (shares, amount_erth, amount_b)
-        } else {
-            let share_erth = amount_erth * pool_info.state.total_shares / pool_info.state.erth_reserve;
-            let share_b = amount_b * pool_info.state.total_shares / pool_info.state.token_b_reserve;
-            let shares = share_erth.min(share_b);
-            let adjusted_amount_erth = (shares * pool_info.state.erth_reserve) / pool_info.state.total_shares;
-            let adjusted_amount_b = (shares * pool_info.state.token_b_reserve) / pool_info.state.total_shares;
-            (shares, adjusted_amount_erth, adjusted_amount_b)
-        };
+    let deposit = calculate_deposit(&pool_info.state, amount_erth, amount_b)?;
+    let shares = deposit.shares;
+    let adjusted_amount_erth = deposit.amount_erth;
+    let adjusted_amount_b = deposit.amount_b;
+
+    // Unstaked liquidity is handed out as LP tokens
+    let lp_token = match (stake, &pool_info.config.lp_token) {
+        (true, _) => None,
+        (false, Some(lp_token)) => Some(lp_token.clone()),
+        (false, None) => return Err(StdError::generic_err("Pool has no LP token")),
+    };
 
     // Messages: transfer in the adjusted amounts
-    let messages = vec![
+    let mut messages = vec![
         CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: addrs.erth_token.address.to_string(),
             code_hash: addrs.erth_token.code_hash.clone(),
@@ -95,22 +160,26 @@ pub fn add_liquidity(
     // Update pool reserves
     pool_info.state.erth_reserve += adjusted_amount_erth;
     pool_info.state.token_b_reserve += adjusted_amount_b;
-    pool_info.state.total_shares += shares;
-
+    // Locked shares count toward total_shares but belong to no one
+    pool_info.state.total_shares += shares + deposit.locked_shares;
 
-    // Always stake the shares directly
-    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
-    let mut user_info = user_info_by_pool
-        .get(deps.storage, &info.sender)
-        .unwrap_or_default();
 
-    if user_info.amount_staked > Uint128::zero() {
-        update_user_rewards(&pool_info, &mut user_info)?;
+    if let Some(lp_token) = lp_token {
+        pool_info.state.lp_token_supply += shares;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: lp_token.address.to_string(),
+            code_hash: lp_token.code_hash,
+            msg: to_binary(&snip20::HandleMsg::Mint {
+                recipient: info.sender.to_string(),
+                amount: shares,
+                memo: None,
+                padding: None,
+            })?,
+            funds: vec![],
+        }));
+    } else {
+        stake_shares(deps.storage, &pool_addr, &mut pool_info, &info.sender, shares, env.block.time.seconds())?;
     }
-    user_info.amount_staked += shares;
-    user_info.reward_debt =
-        user_info.amount_staked * pool_info.state.reward_per_token_scaled / SCALING_FACTOR;
-    user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
 
     // Save pool
     POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
@@ -120,70 +189,273 @@ pub fn add_liquidity(
         .add_attribute("action", "add_liquidity")
         .add_attribute("from", info.sender)
         .add_attribute("shares", shares.to_string())
+        .add_attribute("staked", stake.to_string())
+        .add_attribute("locked_shares", deposit.locked_shares.to_string())
         .add_attribute("adjusted_amount_erth", adjusted_amount_erth.to_string())
         .add_attribute("adjusted_amount_b", adjusted_amount_b.to_string()))
 }
 
 
+// Credit shares to a user's staked position, settling rewards first
+pub fn stake_shares(
+    storage: &mut dyn Storage,
+    pool_addr: &Addr,
+    pool_info: &mut PoolInfo,
+    user: &Addr,
+    shares: Uint128,
+    now: u64,
+) -> StdResult<()> {
+    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+    let mut user_info = user_info_by_pool
+        .get(storage, user)
+        .unwrap_or_default();
+
+    if user_info.amount_staked > Uint128::zero() {
+        settle_rewards(pool_info, &mut user_info, now)?;
+    }
+    user_info.amount_staked += shares;
+    pool_info.state.staked_shares += shares;
+    reset_reward_debt(pool_info, &mut user_info);
+    user_info_by_pool.insert(storage, user, &user_info)?;
+    track_user_pool(storage, user, pool_addr)?;
+
+    Ok(())
+}
+
+
 // -------------------------
-// Remove Liquidity (withdraw staked shares)
+// Stake LP tokens (sent in through Receive)
 // -------------------------
-pub fn remove_liquidity(
+pub fn stake_lp(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    from: Addr,
+    amount: Uint128,
     pool: String,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    let burn_msg = burn_lp_tokens(&pool_info, &info.sender, amount)?;
+
+    // LP tokens become staked shares again
+    pool_info.state.lp_token_supply = pool_info.state.lp_token_supply.checked_sub(amount)?;
+    stake_shares(deps.storage, &pool_addr, &mut pool_info, &from, amount, env.block.time.seconds())?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_message(burn_msg)
+        .add_attribute("action", "stake_lp")
+        .add_attribute("from", from)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("shares", amount.to_string()))
+}
+
+
+// -------------------------
+// Redeem LP tokens for the underlying reserves
+// -------------------------
+pub fn redeem_lp(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    from: Addr,
     amount: Uint128,
+    pool: String,
 ) -> StdResult<Response> {
     let pool_addr = deps.api.addr_validate(&pool)?;
-    let state = STATE.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
     let addrs = load_contracts(&deps.as_ref(), &config)?;
     let mut pool_info = POOL_INFO
         .get(deps.storage, &pool_addr)
-        .ok_or_else(|| StdError::generic_err("Pool info not found"))?;
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    let burn_msg = burn_lp_tokens(&pool_info, &info.sender, amount)?;
+
+    // LP tokens were never staked, so there is no unbonding period
+    let amount_erth = amount * pool_info.state.erth_reserve / pool_info.state.total_shares;
+    let amount_b    = amount * pool_info.state.token_b_reserve / pool_info.state.total_shares;
 
+    pool_info.state.erth_reserve    = pool_info.state.erth_reserve.checked_sub(amount_erth)?;
+    pool_info.state.token_b_reserve = pool_info.state.token_b_reserve.checked_sub(amount_b)?;
+    pool_info.state.total_shares    = pool_info.state.total_shares.checked_sub(amount)?;
+    pool_info.state.lp_token_supply = pool_info.state.lp_token_supply.checked_sub(amount)?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    let transfer_erth_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: addrs.erth_token.address.to_string(),
+        code_hash: addrs.erth_token.code_hash.clone(),
+        msg: to_binary(&snip20::HandleMsg::Transfer {
+            recipient: from.to_string(),
+            amount: amount_erth,
+            padding: None,
+            memo: None,
+        })?,
+        funds: vec![],
+    });
+    let transfer_b_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: pool_info.config.token_b_contract.to_string(),
+        code_hash: pool_info.config.token_b_hash.clone(),
+        msg: to_binary(&snip20::HandleMsg::Transfer {
+            recipient: from.to_string(),
+            amount: amount_b,
+            padding: None,
+            memo: None,
+        })?,
+        funds: vec![],
+    });
+
+    Ok(Response::new()
+        .add_message(burn_msg)
+        .add_message(transfer_erth_msg)
+        .add_message(transfer_b_msg)
+        .add_attribute("action", "redeem_lp")
+        .add_attribute("from", from)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("shares_redeemed", amount.to_string())
+        .add_attribute("erth_returned", amount_erth.to_string())
+        .add_attribute("token_b_returned", amount_b.to_string()))
+}
+
+// Checks the sender is the pool's LP token and burns the tokens it sent us
+fn burn_lp_tokens(
+    pool_info: &PoolInfo,
+    token: &Addr,
+    amount: Uint128,
+) -> StdResult<CosmosMsg> {
+    let lp_token = pool_info
+        .config
+        .lp_token
+        .as_ref()
+        .ok_or_else(|| StdError::generic_err("Pool has no LP token"))?;
+    if *token != lp_token.address {
+        return Err(StdError::generic_err("invalid token"));
+    }
+    if amount.is_zero() {
+        return Err(StdError::generic_err("amount must be greater than zero"));
+    }
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: lp_token.address.to_string(),
+        code_hash: lp_token.code_hash.clone(),
+        msg: to_binary(&snip20::HandleMsg::Burn {
+            amount,
+            memo: None,
+            padding: None,
+        })?,
+        funds: vec![],
+    }))
+}
+
+
+// Settles rewards, pays them out and removes unlocked shares from a user's stake
+fn unstake_shares(
+    storage: &mut dyn Storage,
+    pool_addr: &Addr,
+    pool_info: &mut PoolInfo,
+    user: &Addr,
+    amount: Uint128,
+    erth_token: &ContractInfo,
+    now: u64,
+) -> StdResult<Vec<CosmosMsg>> {
     // 1) Load user staking info
-    let user_info_by_pool = USER_INFO.add_suffix(&pool_addr.as_bytes());
+    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
     let mut user_info = user_info_by_pool
-        .get(deps.storage, &info.sender)
+        .get(storage, user)
         .ok_or_else(|| StdError::generic_err("User info not found"))?;
 
-    // 2) Update user rewards
-    update_user_rewards(&pool_info, &mut user_info)?;
+    // 2) Update user rewards, then drop any locks that have run out.
+    //    A deprecated pool releases every lock so nobody is stuck in it.
+    let release_time = if pool_info.state.deprecated { u64::MAX } else { now };
+    settle_rewards(pool_info, &mut user_info, release_time)?;
     if user_info.amount_staked < amount {
         return Err(StdError::generic_err("Insufficient staked amount"));
     }
+    if user_info.amount_staked - locked_amount(&user_info) < amount {
+        return Err(StdError::generic_err("Insufficient unlocked staked amount"));
+    }
 
     // 3) Decrease staked
     user_info.amount_staked = user_info.amount_staked.checked_sub(amount)?;
+    pool_info.state.staked_shares = pool_info.state.staked_shares.checked_sub(amount)?;
 
-    // 4) Transfer pending rewards if any
+    // 4) Pay out pending rewards if any. With vesting on they join the
+    //    user's schedule, the same as ClaimRewards.
     let mut messages: Vec<CosmosMsg> = Vec::new();
     if !user_info.pending_rewards.is_zero() {
-        let transfer_rewards_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: addrs.erth_token.address.to_string(),
-            code_hash: addrs.erth_token.code_hash.clone(),
-            msg: to_binary(&snip20::HandleMsg::Transfer {
-                recipient: info.sender.to_string(),
-                amount: user_info.pending_rewards,
-                padding: None,
-                memo: None,
-            })?,
-            funds: vec![],
-        });
-        messages.push(transfer_rewards_msg);
+        let config = CONFIG.load(storage)?;
+        let payout = if config.reward_vesting_seconds > 0 {
+            vest_rewards(storage, user, user_info.pending_rewards, now, config.reward_vesting_seconds)?
+        } else {
+            user_info.pending_rewards
+        };
+        if !payout.is_zero() {
+            messages.push(erth_transfer_msg(erth_token, user, payout)?);
+        }
         user_info.pending_rewards = Uint128::zero();
     }
 
-    // 5) Update or remove user info
-    if user_info.amount_staked.is_zero() {
-        user_info_by_pool.remove(deps.storage, &info.sender)?;
+    // 5) Update or remove user info. Unclaimed incentives keep the entry
+    //    around for ClaimIncentives.
+    reset_reward_debt(pool_info, &mut user_info);
+    if user_info.amount_staked.is_zero() && !has_pending_incentives(&user_info) {
+        user_info_by_pool.remove(storage, user)?;
     } else {
-        user_info.reward_debt =
-            user_info.amount_staked * pool_info.state.reward_per_token_scaled / SCALING_FACTOR;
-        user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
+        user_info_by_pool.insert(storage, user, &user_info)?;
+    }
+
+    Ok(messages)
+}
+
+
+// -------------------------
+// Remove Liquidity (withdraw staked shares)
+// -------------------------
+pub fn remove_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let state = STATE.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool info not found"))?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    // Settle rewards and take the shares out of the user's stake
+    let mut messages = unstake_shares(
+        deps.storage, &pool_addr, &mut pool_info, &info.sender, amount, &addrs.erth_token,
+        env.block.time.seconds(),
+    )?;
+
+    // Deprecated pools pay out right away, without unbonding
+    if pool_info.state.deprecated {
+        let withdrawal = withdraw_shares(
+            &mut pool_info, &config, &addrs.erth_token.address, amount, Uint128::zero(),
+        )?;
+        POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+        messages.extend(withdrawal_messages(&pool_info, &addrs.erth_token, &info.sender, &withdrawal)?);
+
+        return Ok(Response::new()
+            .add_messages(messages)
+            .add_attribute("action", "remove_liquidity_deprecated")
+            .add_attribute("shares_withdrawn", amount.to_string())
+            .add_attribute("erth_returned", withdrawal.erth.to_string())
+            .add_attribute("token_b_returned", withdrawal.token_b.to_string()));
     }
+
     POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
     STATE.save(deps.storage, &state)?;
 
@@ -199,11 +471,17 @@ pub fn remove_liquidity(
         .get(deps.storage, &info.sender)
         .unwrap_or_default();
 
+    // Pending requests keep the settings they were made under
     let now = env.block.time.seconds();
+    let unbonding_seconds = pool_info.config.unbonding_seconds.unwrap_or(config.unbonding_seconds);
+    let unbonding_window = pool_info.config.unbonding_window.unwrap_or(config.unbonding_window);
     unbond_records.push(UnbondRecord {
         pool: pool_addr,
         amount,
         start_time: now,
+        unbonding_seconds,
+        unbonding_window,
+        late_claim_policy: pool_info.config.late_claim_policy.clone(),
     });
     unbonding_by_pool.insert(deps.storage, &info.sender, &unbond_records)?;
 
@@ -212,7 +490,7 @@ pub fn remove_liquidity(
         .add_messages(messages)
         .add_attribute("action", "remove_liquidity_and_unbond")
         .add_attribute("shares_withdrawn", amount.to_string())
-        .add_attribute("claimable_at", (now + config.unbonding_seconds).to_string()))
+        .add_attribute("claimable_at", (now + unbonding_seconds).to_string()))
 }
 
 
@@ -220,139 +498,851 @@ pub fn remove_liquidity(
 
 
 // -------------------------
-//  CLAIM Unbond
+// Transfer Position (move staked shares to another address)
 // -------------------------
-pub fn claim_unbond_liquidity(
+pub fn transfer_position(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     pool: String,
-) -> Result<Response, StdError> {
-    let user = info.sender.clone();
+    recipient: String,
+    shares: Uint128,
+) -> StdResult<Response> {
     let pool_addr = deps.api.addr_validate(&pool)?;
-    let mut pool_info = POOL_INFO
-        .get(deps.storage, &pool_addr)
-        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
-
+    let recipient = deps.api.addr_validate(&recipient)?;
     let config = CONFIG.load(deps.storage)?;
     let addrs = load_contracts(&deps.as_ref(), &config)?;
-    let now = env.block.time.seconds();
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool info not found"))?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
 
-    let unbonding_by_pool = UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
-    let records = unbonding_by_pool
-        .get(deps.storage, &user)
-        .unwrap_or_default();
-    if records.is_empty() {
-        return Err(StdError::generic_err("No unbond requests found"));
+    if shares.is_zero() {
+        return Err(StdError::generic_err("shares must be greater than zero"));
     }
-
-    // Separate into those ready to claim vs still pending
-    let required_wait = config.unbonding_seconds;
-    let unbonding_window = config.unbonding_window;
-    
-    // Partition records into ready_to_claim, auto_restake, and still_pending
-    let (ready_to_claim, past_ready): (Vec<UnbondRecord>, Vec<UnbondRecord>) =
-        records.into_iter()
-               .partition(|r| now >= r.start_time + required_wait && now <= r.start_time + required_wait + unbonding_window);
-    
-    let (auto_restake, still_pending): (Vec<UnbondRecord>, Vec<UnbondRecord>) =
-        past_ready.into_iter()
-                  .partition(|r| now > r.start_time + required_wait + unbonding_window);
-
-    if ready_to_claim.is_empty() && auto_restake.is_empty() {
-        return Err(StdError::generic_err("No unbonding requests are ready yet"));
+    if recipient == info.sender {
+        return Err(StdError::generic_err("Cannot transfer a position to yourself"));
     }
 
-    // Handle auto-restaking first
-    let auto_restake_shares: Uint128 = auto_restake.iter().map(|r| r.amount).sum();
-    if !auto_restake_shares.is_zero() {
-        // Auto-restake the shares
-        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
-        let mut user_info = user_info_by_pool
-            .get(deps.storage, &user)
-            .unwrap_or_default();
+    // Both sides settle at the current reward rate, so rewards earned so far stay
+    // with the sender and the recipient only earns from here on
+    let messages = unstake_shares(
+        deps.storage, &pool_addr, &mut pool_info, &info.sender, shares, &addrs.erth_token,
+        env.block.time.seconds(),
+    )?;
+    stake_shares(deps.storage, &pool_addr, &mut pool_info, &recipient, shares, env.block.time.seconds())?;
+    prune_user_pools(deps.storage, &info.sender)?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
 
-        if user_info.amount_staked > Uint128::zero() {
-            update_user_rewards(&pool_info, &mut user_info)?;
-        }
-        
-        user_info.amount_staked += auto_restake_shares;
-        // Note: total_shares is NOT incremented — these shares were never subtracted
-        // from total_shares during remove_liquidity, they only moved to unbonding_shares.
-        user_info.reward_debt =
-            user_info.amount_staked * pool_info.state.reward_per_token_scaled / SCALING_FACTOR;
-        user_info_by_pool.insert(deps.storage, &user, &user_info)?;
-        
-        // Remove from unbonding_shares
-        pool_info.state.unbonding_shares =
-            pool_info.state.unbonding_shares.checked_sub(auto_restake_shares)?;
-    }
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "transfer_position")
+        .add_attribute("from", info.sender)
+        .add_attribute("recipient", recipient)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("shares", shares.to_string()))
+}
 
-    // Sum up total shares to claim (only ready_to_claim)
-    let total_shares: Uint128 = ready_to_claim.iter().map(|r| r.amount).sum();
 
-    // Overwrite storage with only still-pending
-    unbonding_by_pool.insert(deps.storage, &user, &still_pending)?;
+// Reserves paid out for withdrawn shares, after any penalty
+struct Withdrawal {
+    erth: Uint128,
+    token_b: Uint128,
+    penalty_erth: Uint128,
+    penalty_b: Uint128,
+    penalty_burned: Uint128,
+}
 
-    // Calculate underlying tokens
+// Removes shares and their reserves from the pool. The penalty, in basis points,
+// is burned or left in the reserves for the remaining LPs depending on config.
+fn withdraw_shares(
+    pool_info: &mut PoolInfo,
+    config: &Config,
+    erth_token: &Addr,
+    shares: Uint128,
+    penalty_rate: Uint128,
+) -> StdResult<Withdrawal> {
     if pool_info.state.total_shares.is_zero() {
         return Err(StdError::generic_err("Pool has zero total shares"));
     }
-    let amount_erth = total_shares * pool_info.state.erth_reserve / pool_info.state.total_shares;
-    let amount_b    = total_shares * pool_info.state.token_b_reserve / pool_info.state.total_shares;
+    // Nobody is left to receive the penalty or to swap it through
+    let penalty_rate = if shares == pool_info.state.total_shares {
+        Uint128::zero()
+    } else {
+        penalty_rate
+    };
 
-    // Decrement unbonding_shares by total_shares
-    pool_info.state.unbonding_shares =
-        pool_info.state.unbonding_shares.checked_sub(total_shares)?;
-    
-    // Update pool reserves
-    pool_info.state.erth_reserve    = pool_info.state.erth_reserve.checked_sub(amount_erth)?;
-    pool_info.state.token_b_reserve = pool_info.state.token_b_reserve.checked_sub(amount_b)?;
-    pool_info.state.total_shares    = pool_info.state.total_shares.checked_sub(total_shares)?;
-    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+    // Underlying tokens, minus the penalty
+    let amount_erth = shares * pool_info.state.erth_reserve / pool_info.state.total_shares;
+    let amount_b    = shares * pool_info.state.token_b_reserve / pool_info.state.total_shares;
+    let penalty_erth = amount_erth * penalty_rate / Uint128::from(10000u128);
+    let penalty_b    = amount_b * penalty_rate / Uint128::from(10000u128);
+    let payout_erth = amount_erth - penalty_erth;
+    let payout_b    = amount_b - penalty_b;
 
-    let transfer_erth_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: addrs.erth_token.address.to_string(),
-        code_hash: addrs.erth_token.code_hash.clone(),
+    // Remove the payout; the penalty stays in the reserves unless burned
+    pool_info.state.erth_reserve    = pool_info.state.erth_reserve.checked_sub(payout_erth)?;
+    pool_info.state.token_b_reserve = pool_info.state.token_b_reserve.checked_sub(payout_b)?;
+    pool_info.state.total_shares    = pool_info.state.total_shares.checked_sub(shares)?;
+
+    let mut penalty_burned = Uint128::zero();
+    if config.burn_unbond_penalties {
+        // Same as protocol fees: convert the token_b part to ERTH in the pool and burn it all
+        pool_info.state.erth_reserve = pool_info.state.erth_reserve.checked_sub(penalty_erth)?;
+        let penalty_b_in_erth = if penalty_b.is_zero() {
+            Uint128::zero()
+        } else {
+            pool_info.state.token_b_reserve = pool_info.state.token_b_reserve.checked_sub(penalty_b)?;
+            let erth_out = calculate_amm_swap(
+                erth_token, config.protocol_fee, pool_info,
+                &pool_info.config.token_b_contract, penalty_b, false,
+            )?.output_amount;
+            pool_info.state.token_b_reserve += penalty_b;
+            pool_info.state.erth_reserve = pool_info.state.erth_reserve.checked_sub(erth_out)?;
+            erth_out
+        };
+        penalty_burned = penalty_erth + penalty_b_in_erth;
+    }
+
+    Ok(Withdrawal {
+        erth: payout_erth,
+        token_b: payout_b,
+        penalty_erth,
+        penalty_b,
+        penalty_burned,
+    })
+}
+
+// Messages paying out both sides of a withdrawal
+fn withdrawal_messages(
+    pool_info: &PoolInfo,
+    erth_token: &ContractInfo,
+    recipient: &Addr,
+    withdrawal: &Withdrawal,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages = vec![];
+
+    if !withdrawal.penalty_burned.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: erth_token.address.to_string(),
+            code_hash: erth_token.code_hash.clone(),
+            msg: to_binary(&snip20::HandleMsg::Burn {
+                amount: withdrawal.penalty_burned,
+                memo: None,
+                padding: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: erth_token.address.to_string(),
+        code_hash: erth_token.code_hash.clone(),
         msg: to_binary(&snip20::HandleMsg::Transfer {
-            recipient: user.to_string(),
-            amount: amount_erth,
+            recipient: recipient.to_string(),
+            amount: withdrawal.erth,
             padding: None,
             memo: None,
         })?,
         funds: vec![],
-    });
-    let transfer_b_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+    }));
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: pool_info.config.token_b_contract.to_string(),
         code_hash: pool_info.config.token_b_hash.clone(),
         msg: to_binary(&snip20::HandleMsg::Transfer {
-            recipient: user.to_string(),
-            amount: amount_b,
+            recipient: recipient.to_string(),
+            amount: withdrawal.token_b,
             padding: None,
             memo: None,
         })?,
         funds: vec![],
-    });
+    }));
+
+    Ok(messages)
+}
+
+
+// -------------------------
+// Instant Remove Liquidity (skip unbonding for a penalty)
+// -------------------------
+pub fn instant_remove_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    amount: Uint128,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut state = STATE.load(deps.storage)?;
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool info not found"))?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    if amount.is_zero() {
+        return Err(StdError::generic_err("amount must be greater than zero"));
+    }
+    // The penalty needs remaining liquidity to stay in or be swapped through
+    if !pool_info.state.deprecated && amount >= pool_info.state.total_shares {
+        return Err(StdError::generic_err("Cannot instantly remove all pool liquidity, use remove_liquidity"));
+    }
+
+    let mut messages = unstake_shares(
+        deps.storage, &pool_addr, &mut pool_info, &info.sender, amount, &addrs.erth_token,
+        env.block.time.seconds(),
+    )?;
+
+    // No penalty for leaving a deprecated pool
+    let penalty = if pool_info.state.deprecated {
+        Uint128::zero()
+    } else {
+        config.instant_unbond_penalty
+    };
+    let withdrawal = withdraw_shares(
+        &mut pool_info, &config, &addrs.erth_token.address, amount, penalty,
+    )?;
+    state.erth_burned += withdrawal.penalty_burned;
+
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+    STATE.save(deps.storage, &state)?;
+
+    messages.extend(withdrawal_messages(&pool_info, &addrs.erth_token, &info.sender, &withdrawal)?);
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "instant_remove_liquidity")
+        .add_attribute("user", info.sender)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("shares_withdrawn", amount.to_string())
+        .add_attribute("erth_returned", withdrawal.erth.to_string())
+        .add_attribute("token_b_returned", withdrawal.token_b.to_string())
+        .add_attribute("penalty_erth", withdrawal.penalty_erth.to_string())
+        .add_attribute("penalty_token_b", withdrawal.penalty_b.to_string())
+        .add_attribute("penalty_burned", withdrawal.penalty_burned.to_string()))
+}
+
+
+// Outcome of settling a user's unbond requests in one pool
+pub struct UnbondClaim {
+    pub shares_claimed: Uint128,
+    pub shares_restaked: Uint128,
+    pub erth_returned: Uint128,
+    pub token_b_returned: Uint128,
+    pub penalty_burned: Uint128,
+    pub messages: Vec<CosmosMsg>,
+}
+
+// Pays out matured requests and applies each late record's policy.
+// Returns None when nothing is ready, leaving storage untouched.
+pub fn settle_unbond_requests(
+    storage: &mut dyn Storage,
+    now: u64,
+    user: &Addr,
+    pool_addr: &Addr,
+    config: &Config,
+    erth_token: &ContractInfo,
+) -> StdResult<Option<UnbondClaim>> {
+    let mut pool_info = POOL_INFO
+        .get(storage, pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    accrue_rewards(&mut pool_info, now)?;
+
+    let unbonding_by_pool = UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
+    let records = unbonding_by_pool
+        .get(storage, user)
+        .unwrap_or_default();
+
+    // Sort records by the settings stored on each of them
+    let mut ready_to_claim: Vec<(Uint128, Uint128)> = vec![];
+    let mut auto_restake_shares = Uint128::zero();
+    let mut still_pending: Vec<UnbondRecord> = vec![];
+    for record in records {
+        let matures_at = record.start_time + record.unbonding_seconds;
+        let mut window_ends_at = matures_at + record.unbonding_window;
+        if record.late_claim_policy == LateClaimPolicy::ExtendOnce {
+            window_ends_at += record.unbonding_window;
+        }
+
+        if pool_info.state.deprecated {
+            // Everything is claimable at once and nothing restakes into a dead pool
+            ready_to_claim.push((record.amount, Uint128::zero()));
+        } else if now < matures_at {
+            still_pending.push(record);
+        } else if now <= window_ends_at {
+            ready_to_claim.push((record.amount, Uint128::zero()));
+        } else {
+            match record.late_claim_policy {
+                LateClaimPolicy::LatePenalty { penalty } => ready_to_claim.push((record.amount, penalty)),
+                LateClaimPolicy::AutoRestake | LateClaimPolicy::ExtendOnce =>
+                    auto_restake_shares += record.amount,
+            }
+        }
+    }
+
+    if ready_to_claim.is_empty() && auto_restake_shares.is_zero() {
+        return Ok(None);
+    }
+
+    // Handle auto-restaking first
+    if !auto_restake_shares.is_zero() {
+        // Note: total_shares is NOT incremented — these shares were never subtracted
+        // from total_shares during remove_liquidity, they only moved to unbonding_shares.
+        stake_shares(storage, pool_addr, &mut pool_info, user, auto_restake_shares, now)?;
+        pool_info.state.unbonding_shares =
+            pool_info.state.unbonding_shares.checked_sub(auto_restake_shares)?;
+    }
+
+    // Overwrite storage with only still-pending
+    unbonding_by_pool.insert(storage, user, &still_pending)?;
+
+    // Withdraw each claimable record with its own penalty and pay out the total
+    let mut claim = UnbondClaim {
+        shares_claimed: Uint128::zero(),
+        shares_restaked: auto_restake_shares,
+        erth_returned: Uint128::zero(),
+        token_b_returned: Uint128::zero(),
+        penalty_burned: Uint128::zero(),
+        messages: vec![],
+    };
+    let mut total = Withdrawal {
+        erth: Uint128::zero(),
+        token_b: Uint128::zero(),
+        penalty_erth: Uint128::zero(),
+        penalty_b: Uint128::zero(),
+        penalty_burned: Uint128::zero(),
+    };
+    for (shares, penalty) in ready_to_claim {
+        let withdrawal = withdraw_shares(&mut pool_info, config, &erth_token.address, shares, penalty)?;
+        pool_info.state.unbonding_shares =
+            pool_info.state.unbonding_shares.checked_sub(shares)?;
+        claim.shares_claimed += shares;
+        total.erth += withdrawal.erth;
+        total.token_b += withdrawal.token_b;
+        total.penalty_erth += withdrawal.penalty_erth;
+        total.penalty_b += withdrawal.penalty_b;
+        total.penalty_burned += withdrawal.penalty_burned;
+    }
+    POOL_INFO.insert(storage, pool_addr, &pool_info)?;
+
+    if !claim.shares_claimed.is_zero() {
+        claim.erth_returned = total.erth;
+        claim.token_b_returned = total.token_b;
+        claim.penalty_burned = total.penalty_burned;
+        claim.messages = withdrawal_messages(&pool_info, erth_token, user, &total)?;
+    }
+
+    Ok(Some(claim))
+}
+
+
+// -------------------------
+//  CLAIM Unbond
+// -------------------------
+pub fn claim_unbond_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+) -> Result<Response, StdError> {
+    let user = info.sender.clone();
+    claim_unbond(deps, env, user, pool, "claim_unbonding")
+}
+
+// Permissionless payout for users who opted in to auto claiming
+pub fn claim_unbond_for(
+    deps: DepsMut,
+    env: Env,
+    pool: String,
+    user: String,
+) -> Result<Response, StdError> {
+    let user = deps.api.addr_validate(&user)?;
+    if !AUTO_CLAIM_UNBOND.get(deps.storage, &user).unwrap_or(false) {
+        return Err(StdError::generic_err("User has not opted in to auto claiming"));
+    }
+    claim_unbond(deps, env, user, pool, "auto_claim_unbonding")
+}
+
+pub fn set_auto_claim_unbond(
+    deps: DepsMut,
+    info: MessageInfo,
+    enabled: bool,
+) -> StdResult<Response> {
+    if enabled {
+        AUTO_CLAIM_UNBOND.insert(deps.storage, &info.sender, &true)?;
+    } else {
+        AUTO_CLAIM_UNBOND.remove(deps.storage, &info.sender)?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("action", "set_auto_claim_unbond")
+        .add_attribute("user", info.sender)
+        .add_attribute("enabled", enabled.to_string()))
+}
+
+// -------------------------
+//  CLAIM all matured unbonds across pools
+// -------------------------
+pub fn claim_all_unbonded(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> StdResult<Response> {
+    let user = info.sender;
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let now = env.block.time.seconds();
+
+    let mut results: Vec<UnbondClaimResult> = vec![];
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut penalty_burned = Uint128::zero();
+
+    // Pools with nothing ready are skipped rather than failing the whole claim
+    for pool_addr in USER_POOLS.get(deps.storage, &user).unwrap_or_default() {
+        if !POOL_INFO.contains(deps.storage, &pool_addr) {
+            continue;
+        }
+        let claim = match settle_unbond_requests(
+            deps.storage, now, &user, &pool_addr, &config, &addrs.erth_token,
+        )? {
+            Some(claim) => claim,
+            None => continue,
+        };
+
+        penalty_burned += claim.penalty_burned;
+        messages.extend(claim.messages);
+        results.push(UnbondClaimResult {
+            pool: pool_addr,
+            shares_claimed: claim.shares_claimed,
+            shares_restaked: claim.shares_restaked,
+            erth_returned: claim.erth_returned,
+            token_b_returned: claim.token_b_returned,
+        });
+    }
+    prune_user_pools(deps.storage, &user)?;
+
+    if !penalty_burned.is_zero() {
+        let mut state = STATE.load(deps.storage)?;
+        state.erth_burned += penalty_burned;
+        STATE.save(deps.storage, &state)?;
+    }
 
     let mut response = Response::new()
-        .add_attribute("action", "claim_unbonding")
+        .add_messages(messages)
+        .add_attribute("action", "claim_all_unbonded")
         .add_attribute("user", user)
-        .add_attribute("pool", pool_addr);
+        .add_attribute("pools_claimed", results.len().to_string());
+    for result in results.iter() {
+        response = response
+            .add_attribute("pool", result.pool.to_string())
+            .add_attribute("shares_claimed", result.shares_claimed.to_string())
+            .add_attribute("shares_restaked", result.shares_restaked.to_string());
+    }
 
-    if !total_shares.is_zero() {
+    Ok(response.set_data(to_binary(&results)?))
+}
+
+fn claim_unbond(
+    deps: DepsMut,
+    env: Env,
+    user: Addr,
+    pool: String,
+    action: &str,
+) -> Result<Response, StdError> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let now = env.block.time.seconds();
+
+    let unbonding_by_pool = UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
+    if unbonding_by_pool.get(deps.storage, &user).unwrap_or_default().is_empty() {
+        return Err(StdError::generic_err("No unbond requests found"));
+    }
+
+    let claim = settle_unbond_requests(
+        deps.storage, now, &user, &pool_addr, &config, &addrs.erth_token,
+    )?
+    .ok_or_else(|| StdError::generic_err("No unbonding requests are ready yet"))?;
+
+    if !claim.penalty_burned.is_zero() {
+        let mut state = STATE.load(deps.storage)?;
+        state.erth_burned += claim.penalty_burned;
+        STATE.save(deps.storage, &state)?;
+    }
+
+    let mut response = Response::new()
+        .add_messages(claim.messages)
+        .add_attribute("action", action)
+        .add_attribute("user", user)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("total_shares_claimed", claim.shares_claimed.to_string());
+
+    if !claim.shares_claimed.is_zero() {
         response = response
-            .add_message(transfer_erth_msg)
-            .add_message(transfer_b_msg)
-            .add_attribute("total_shares_claimed", total_shares.to_string())
-            .add_attribute("erth_returned", amount_erth.to_string())
-            .add_attribute("token_b_returned", amount_b.to_string());
-    } else {
-        response = response.add_attribute("total_shares_claimed", "0");
+            .add_attribute("erth_returned", claim.erth_returned.to_string())
+            .add_attribute("token_b_returned", claim.token_b_returned.to_string());
     }
 
-    if !auto_restake_shares.is_zero() {
-        response = response.add_attribute("auto_restaked_shares", auto_restake_shares.to_string());
+    if !claim.penalty_burned.is_zero() {
+        response = response.add_attribute("late_penalty_burned", claim.penalty_burned.to_string());
+    }
+
+    if !claim.shares_restaked.is_zero() {
+        response = response.add_attribute("auto_restaked_shares", claim.shares_restaked.to_string());
     }
 
     Ok(response)
 }
+
+
+// -------------------------
+//  CANCEL Unbond
+// -------------------------
+pub fn cancel_unbond(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    indices: Option<Vec<u32>>,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    let unbonding_by_pool = UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
+    let records = unbonding_by_pool
+        .get(deps.storage, &info.sender)
+        .unwrap_or_default();
+    if records.is_empty() {
+        return Err(StdError::generic_err("No unbond requests found"));
+    }
+
+    // Split the chosen records from the ones that keep unbonding
+    let (cancelled, still_pending): (Vec<UnbondRecord>, Vec<UnbondRecord>) = match indices {
+        None => (records, vec![]),
+        Some(indices) => {
+            if indices.is_empty() {
+                return Err(StdError::generic_err("No unbond requests selected"));
+            }
+            for (i, index) in indices.iter().enumerate() {
+                if *index as usize >= records.len() {
+                    return Err(StdError::generic_err(format!("Invalid unbond request index {}", index)));
+                }
+                if indices[..i].contains(index) {
+                    return Err(StdError::generic_err(format!("Duplicate unbond request index {}", index)));
+                }
+            }
+            let (cancelled, still_pending): (Vec<_>, Vec<_>) = records
+                .into_iter()
+                .enumerate()
+                .partition(|(i, _)| indices.contains(&(*i as u32)));
+            (
+                cancelled.into_iter().map(|(_, r)| r).collect(),
+                still_pending.into_iter().map(|(_, r)| r).collect(),
+            )
+        }
+    };
+
+    // Shares never left total_shares, so they only move back from unbonding_shares
+    let cancelled_shares: Uint128 = cancelled.iter().map(|r| r.amount).sum();
+    pool_info.state.unbonding_shares =
+        pool_info.state.unbonding_shares.checked_sub(cancelled_shares)?;
+    stake_shares(deps.storage, &pool_addr, &mut pool_info, &info.sender, cancelled_shares, env.block.time.seconds())?;
+
+    unbonding_by_pool.insert(deps.storage, &info.sender, &still_pending)?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "cancel_unbond")
+        .add_attribute("user", info.sender)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("requests_cancelled", cancelled.len().to_string())
+        .add_attribute("shares_restaked", cancelled_shares.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+    use crate::execute::SCALING_FACTOR;
+    use crate::state::{PoolConfig, REWARD_VESTING};
+    use crate::execute::lock::add_lock;
+
+    fn pool_state(total_shares: u128, erth_reserve: u128, token_b_reserve: u128) -> PoolState {
+        PoolState {
+            total_shares: Uint128::new(total_shares),
+            reward_per_token_scaled: Uint256::zero(),
+            erth_reserve: Uint128::new(erth_reserve),
+            token_b_reserve: Uint128::new(token_b_reserve),
+            reward_history: vec![],
+            volume_history: vec![],
+            history_start: 0,
+            unbonding_shares: Uint128::zero(),
+            staked_shares: Uint128::zero(),
+            lp_token_supply: Uint128::zero(),
+            boosted_shares: Uint128::zero(),
+            boost_expiries: vec![],
+            deprecated: false,
+            incentives: vec![],
+            reward_rate: Uint256::zero(),
+            period_finish: 0,
+            last_reward_update: 0,
+            undistributed_rewards: Uint128::zero(),
+        }
+    }
+
+    // Value of `shares` in ERTH-side reserves after the deposit has landed
+    fn erth_value(state: &PoolState, shares: Uint128) -> Uint128 {
+        shares.multiply_ratio(state.erth_reserve, state.total_shares)
+    }
+
+    fn apply(state: &mut PoolState, deposit: &Deposit) {
+        state.erth_reserve += deposit.amount_erth;
+        state.token_b_reserve += deposit.amount_b;
+        state.total_shares += deposit.shares + deposit.locked_shares;
+    }
+
+    #[test]
+    fn first_deposit_locks_minimum_liquidity() {
+        let state = pool_state(0, 0, 0);
+        let deposit = calculate_deposit(&state, Uint128::new(10_000), Uint128::new(10_000)).unwrap();
+        assert_eq!(deposit.shares, Uint128::new(9_000));
+        assert_eq!(deposit.locked_shares, MINIMUM_LIQUIDITY);
+
+        // A dust first deposit can't leave the attacker owning a one-share supply
+        let err = calculate_deposit(&state, Uint128::new(1), Uint128::new(1)).unwrap_err();
+        assert!(err.to_string().contains("Initial liquidity too small"));
+        let err = calculate_deposit(&state, Uint128::new(1_000), Uint128::new(1_000)).unwrap_err();
+        assert!(err.to_string().contains("Initial liquidity too small"));
+    }
+
+    #[test]
+    fn donation_inflation_attack_is_unprofitable() {
+        // Attacker seeds the pool with the smallest allowed deposit: one share of 1_001
+        let mut state = pool_state(0, 0, 0);
+        let attacker = calculate_deposit(&state, Uint128::new(1_001), Uint128::new(1_001)).unwrap();
+        assert_eq!(attacker.shares, Uint128::new(1));
+        apply(&mut state, &attacker);
+
+        // ...then donates into the reserves, e.g. through penalties left in the pool
+        let donation = Uint128::new(1_000_000_000);
+        state.erth_reserve += donation;
+        state.token_b_reserve += donation;
+
+        // A victim deposits less than the inflated price of a single share held by
+        // the attacker alone would have been
+        let victim_amount = Uint128::new(500_000_000);
+        let victim = calculate_deposit(&state, victim_amount, victim_amount).unwrap();
+        assert!(!victim.shares.is_zero());
+        apply(&mut state, &victim);
+
+        // The victim keeps almost everything, the attacker loses almost all of the donation
+        let victim_value = erth_value(&state, victim.shares);
+        assert!(victim_value * Uint128::new(100) >= victim_amount * Uint128::new(99));
+        let attacker_value = erth_value(&state, attacker.shares);
+        assert!(attacker_value * Uint128::new(100) < donation);
+    }
+
+    #[test]
+    fn deposit_rounding_to_zero_shares_is_rejected() {
+        // The state an unlocked one-share pool could reach after a donation
+        let state = pool_state(1, 1_000_000_001, 1_000_000_001);
+        let err = calculate_deposit(&state, Uint128::new(500_000_000), Uint128::new(500_000_000)).unwrap_err();
+        assert!(err.to_string().contains("Deposit too small"));
+    }
+
+    #[test]
+    fn deposits_round_in_favor_of_the_pool() {
+        // Reserves that don't divide evenly into shares
+        let states = [
+            pool_state(2, 3, 3),
+            pool_state(3, 10, 7),
+            pool_state(1_001, 1_000_001_001, 999_999_999),
+            pool_state(7_777, 123_456_789, 987_654_321),
+        ];
+        let amounts = [2u128, 5, 13, 1_000, 123_457, 50_000_000];
+
+        for state in states.iter() {
+            for amount in amounts.iter() {
+                let deposit = match calculate_deposit(state, Uint128::new(*amount), Uint128::new(*amount)) {
+                    Ok(deposit) => deposit,
+                    Err(_) => continue,
+                };
+                assert!(deposit.amount_erth <= Uint128::new(*amount));
+                assert!(deposit.amount_b <= Uint128::new(*amount));
+
+                // Minted shares are never worth more than what was taken on either side
+                let mut after = state.clone();
+                apply(&mut after, &deposit);
+                assert!(deposit.shares.full_mul(after.erth_reserve) <= deposit.amount_erth.full_mul(after.total_shares));
+                assert!(deposit.shares.full_mul(after.token_b_reserve) <= deposit.amount_b.full_mul(after.total_shares));
+            }
+        }
+    }
+
+    fn pool_info(state: PoolState) -> PoolInfo {
+        PoolInfo {
+            state,
+            config: PoolConfig {
+                token_b_contract: Addr::unchecked("token_b"),
+                token_b_hash: "hash".to_string(),
+                token_b_symbol: "TKB".to_string(),
+                lp_token: None,
+                unbonding_seconds: None,
+                unbonding_window: None,
+                late_claim_policy: LateClaimPolicy::AutoRestake,
+            },
+        }
+    }
+
+    // Streams `amount` ERTH between 0 and `duration`
+    fn start_stream(pool_info: &mut PoolInfo, amount: u128, duration: u64) {
+        pool_info.state.reward_rate = Uint256::from(amount) * SCALING_FACTOR / Uint256::from(duration);
+        pool_info.state.period_finish = duration;
+        pool_info.state.last_reward_update = 0;
+    }
+
+    fn claimable(storage: &dyn Storage, pool_addr: &Addr, pool_info: &PoolInfo, user: &Addr) -> Uint128 {
+        let mut user_info = USER_INFO.add_suffix(pool_addr.as_bytes()).get(storage, user).unwrap();
+        settle_rewards(&mut pool_info.clone(), &mut user_info, pool_info.state.last_reward_update).unwrap();
+        user_info.pending_rewards
+    }
+
+    #[test]
+    fn unbonding_shares_do_not_dilute_rewards() {
+        let mut storage = MockStorage::new();
+        let pool_addr = Addr::unchecked("pool");
+        let erth_token = ContractInfo { address: Addr::unchecked("erth"), code_hash: "hash".to_string() };
+        let (alice, bob) = (Addr::unchecked("alice"), Addr::unchecked("bob"));
+
+        let mut pool_info = pool_info(pool_state(200, 200, 200));
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), 0).unwrap();
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &bob, Uint128::new(100), 0).unwrap();
+        start_stream(&mut pool_info, 1_000_000, 100);
+
+        // Alice starts unbonding right away: her shares stay in total_shares
+        unstake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), &erth_token, 0).unwrap();
+        pool_info.state.unbonding_shares += Uint128::new(100);
+        assert_eq!(pool_info.state.total_shares, Uint128::new(200));
+        assert_eq!(pool_info.state.staked_shares, Uint128::new(100));
+
+        // Bob, the only staker left, earns the whole stream
+        accrue_rewards(&mut pool_info, 100).unwrap();
+        assert_eq!(claimable(&storage, &pool_addr, &pool_info, &bob), Uint128::new(1_000_000));
+    }
+
+    #[test]
+    fn unstaked_shares_leave_nothing_stranded() {
+        let mut storage = MockStorage::new();
+        let pool_addr = Addr::unchecked("pool");
+        let (alice, bob) = (Addr::unchecked("alice"), Addr::unchecked("bob"));
+
+        // Minimum liquidity and LP token shares are part of total_shares but not staked
+        let mut pool_info = pool_info(pool_state(1_000 + 300 + 400, 1_700, 1_700));
+        pool_info.state.lp_token_supply = Uint128::new(400);
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), 0).unwrap();
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &bob, Uint128::new(200), 0).unwrap();
+        start_stream(&mut pool_info, 900_000, 100);
+
+        // Halfway through, Bob stakes more
+        accrue_rewards(&mut pool_info, 50).unwrap();
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &bob, Uint128::new(300), 50).unwrap();
+        accrue_rewards(&mut pool_info, 100).unwrap();
+
+        let alice_rewards = claimable(&storage, &pool_addr, &pool_info, &alice);
+        let bob_rewards = claimable(&storage, &pool_addr, &pool_info, &bob);
+        assert_eq!(alice_rewards, Uint128::new(150_000 + 75_000));
+        assert_eq!(bob_rewards, Uint128::new(300_000 + 375_000));
+        assert_eq!(alice_rewards + bob_rewards, Uint128::new(900_000));
+    }
+
+    #[test]
+    fn late_staker_does_not_collect_backlog() {
+        let mut storage = MockStorage::new();
+        let pool_addr = Addr::unchecked("pool");
+        let alice = Addr::unchecked("alice");
+
+        // Half the stream runs with no one staked
+        let mut pool_info = pool_info(pool_state(100, 100, 100));
+        start_stream(&mut pool_info, 1_000_000, 100);
+        accrue_rewards(&mut pool_info, 50).unwrap();
+        assert_eq!(pool_info.state.undistributed_rewards, Uint128::new(500_000));
+
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), 50).unwrap();
+        accrue_rewards(&mut pool_info, 100).unwrap();
+        assert_eq!(claimable(&storage, &pool_addr, &pool_info, &alice), Uint128::new(500_000));
+        assert_eq!(pool_info.state.undistributed_rewards, Uint128::new(500_000));
+    }
+
+    #[test]
+    fn lock_boost_stops_at_expiry() {
+        const DAY: u64 = 86400;
+        let mut storage = MockStorage::new();
+        let pool_addr = Addr::unchecked("pool");
+        let (alice, bob) = (Addr::unchecked("alice"), Addr::unchecked("bob"));
+
+        let mut pool_info = pool_info(pool_state(200, 200, 200));
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), 0).unwrap();
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &bob, Uint128::new(100), 0).unwrap();
+
+        // Alice locks everything at 2x for the first of three days
+        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+        let mut user_info = user_info_by_pool.get(&storage, &alice).unwrap();
+        add_lock(&mut pool_info, &mut user_info, Uint128::new(100), Uint128::new(100), DAY, 0);
+        user_info_by_pool.insert(&mut storage, &alice, &user_info).unwrap();
+        start_stream(&mut pool_info, 1_000 * 3 * DAY as u128, 3 * DAY);
+
+        // Nobody touches either position until the stream is over
+        accrue_rewards(&mut pool_info, 3 * DAY).unwrap();
+        assert_eq!(pool_info.state.boosted_shares, Uint128::zero());
+        assert_eq!(claimable(&storage, &pool_addr, &pool_info, &alice), Uint128::new(57_600_000 + 86_400_000));
+        assert_eq!(claimable(&storage, &pool_addr, &pool_info, &bob), Uint128::new(28_800_000 + 86_400_000));
+    }
+
+    #[test]
+    fn unstaking_vests_pending_rewards() {
+        let mut storage = MockStorage::new();
+        let pool_addr = Addr::unchecked("pool");
+        let erth_token = ContractInfo { address: Addr::unchecked("erth"), code_hash: "hash".to_string() };
+        let alice = Addr::unchecked("alice");
+        CONFIG.save(&mut storage, &Config {
+            contract_manager: Addr::unchecked("manager"),
+            registry_contract: Addr::unchecked("registry"),
+            registry_hash: "hash".to_string(),
+            unbonding_seconds: 0,
+            unbonding_window: 0,
+            protocol_fee: Uint128::zero(),
+            instant_unbond_penalty: Uint128::zero(),
+            burn_unbond_penalties: false,
+            lock_tiers: vec![],
+            gauge_epoch_seconds: 1,
+            gauge_vote_weight: Uint128::zero(),
+            reward_stream_seconds: 1,
+            allocation_sources: vec![],
+            keeper_bounty: Uint128::zero(),
+            min_upkeep_interval: 0,
+            volume_bucket_seconds: 1,
+            volume_window: 1,
+            volume_decay: Uint128::zero(),
+            undistributed_sink: None,
+            reward_vesting_seconds: 1_000,
+            early_vesting_penalty: Uint128::zero(),
+        }).unwrap();
+
+        let mut pool_info = pool_info(pool_state(100, 100, 100));
+        stake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), 0).unwrap();
+        start_stream(&mut pool_info, 1_000_000, 100);
+        accrue_rewards(&mut pool_info, 100).unwrap();
+
+        // Withdrawing pays nothing out directly; the rewards start vesting
+        let messages = unstake_shares(&mut storage, &pool_addr, &mut pool_info, &alice, Uint128::new(100), &erth_token, 100).unwrap();
+        assert!(messages.is_empty());
+        let entries = REWARD_VESTING.get(&storage, &alice).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].amount, Uint128::new(1_000_000));
+        assert_eq!(entries[0].end_time, 1_100);
+    }
+}
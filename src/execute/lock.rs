@@ -0,0 +1,253 @@
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+
+use crate::execute::{update_user_rewards, reset_reward_debt, accrue_rewards, ensure_pool_active, effective_shares};
+use crate::execute::rewards::settle_at_checkpoint;
+use crate::state::{CONFIG, POOL_INFO, USER_INFO, PoolInfo, PoolState, UserInfo, StakeLock, BoostExpiry};
+
+// Boosts end at the start of the day their lock does, which keeps the number
+// of distinct expiry times per pool bounded
+const BOOST_EXPIRY_GRANULARITY: u64 = 86400;
+
+pub fn boost_end(unlock_time: u64) -> u64 {
+    unlock_time - unlock_time % BOOST_EXPIRY_GRANULARITY
+}
+
+// Shares held in locks that have not expired yet
+pub fn locked_amount(user_info: &UserInfo) -> Uint128 {
+    user_info.locks.iter().map(|lock| lock.amount).sum()
+}
+
+// Counts a new lock's boost in the pool until it ends
+fn schedule_boost(state: &mut PoolState, time: u64, boost_shares: Uint128) {
+    match state.boost_expiries.iter_mut().find(|expiry| expiry.time == time) {
+        Some(expiry) => {
+            expiry.boost_shares += boost_shares;
+            expiry.locks += 1;
+        }
+        None => {
+            let index = state.boost_expiries.partition_point(|expiry| expiry.time < time);
+            state.boost_expiries.insert(index, BoostExpiry {
+                time,
+                boost_shares,
+                locks: 1,
+                checkpoint: None,
+            });
+        }
+    }
+    state.boosted_shares += boost_shares;
+}
+
+// Takes one lock off the expiry at `time`, dropping it once no lock is left
+fn unschedule_boost(state: &mut PoolState, time: u64, boost_shares: Uint128) -> StdResult<()> {
+    let index = state
+        .boost_expiries
+        .iter()
+        .position(|expiry| expiry.time == time)
+        .ok_or_else(|| StdError::generic_err("Lock boost expiry not found"))?;
+    let expiry = &mut state.boost_expiries[index];
+    if expiry.checkpoint.is_none() {
+        expiry.boost_shares = expiry.boost_shares.checked_sub(boost_shares)?;
+        state.boosted_shares = state.boosted_shares.checked_sub(boost_shares)?;
+    }
+    expiry.locks -= 1;
+    if expiry.locks == 0 {
+        state.boost_expiries.remove(index);
+    }
+    Ok(())
+}
+
+// Settles each of the user's boosts that has ended up to the time it ended,
+// then takes it out of the user's effective shares
+fn settle_ended_boosts(pool_info: &mut PoolInfo, user_info: &mut UserInfo) -> StdResult<()> {
+    let mut ends: Vec<u64> = user_info
+        .locks
+        .iter()
+        .filter(|lock| !lock.boost_shares.is_zero())
+        .map(|lock| boost_end(lock.unlock_time))
+        .collect();
+    ends.sort_unstable();
+    ends.dedup();
+
+    for time in ends {
+        let checkpoint = match pool_info
+            .state
+            .boost_expiries
+            .iter()
+            .find(|expiry| expiry.time == time)
+            .and_then(|expiry| expiry.checkpoint.clone())
+        {
+            Some(checkpoint) => checkpoint,
+            None => continue,
+        };
+        let shares_before = effective_shares(user_info);
+        let mut ended_locks = vec![];
+        for lock in user_info.locks.iter_mut() {
+            if !lock.boost_shares.is_zero() && boost_end(lock.unlock_time) == time {
+                ended_locks.push(lock.boost_shares);
+                lock.boost_shares = Uint128::zero();
+            }
+        }
+        settle_at_checkpoint(&checkpoint, user_info, shares_before, effective_shares(user_info))?;
+        for boost_shares in ended_locks {
+            unschedule_boost(&mut pool_info.state, time, boost_shares)?;
+        }
+    }
+    Ok(())
+}
+
+// Drops locks past their unlock time. A boost still running (only when a
+// deprecated pool releases every lock early) is taken out of the pool too.
+// Rewards must be settled before calling this so the boost counts up to now.
+pub fn release_expired_locks(
+    pool_info: &mut PoolInfo,
+    user_info: &mut UserInfo,
+    now: u64,
+) -> StdResult<Uint128> {
+    let (expired, active): (Vec<StakeLock>, Vec<StakeLock>) = user_info
+        .locks
+        .drain(..)
+        .partition(|lock| now >= lock.unlock_time);
+    user_info.locks = active;
+
+    let mut released_boost = Uint128::zero();
+    for lock in expired.iter().filter(|lock| !lock.boost_shares.is_zero()) {
+        unschedule_boost(&mut pool_info.state, boost_end(lock.unlock_time), lock.boost_shares)?;
+        released_boost += lock.boost_shares;
+    }
+    reset_reward_debt(pool_info, user_info);
+
+    Ok(released_boost)
+}
+
+// Settles rewards, with ended boosts counted only up to when they ended, and
+// then drops expired locks
+pub fn settle_rewards(
+    pool_info: &mut PoolInfo,
+    user_info: &mut UserInfo,
+    now: u64,
+) -> StdResult<()> {
+    settle_ended_boosts(pool_info, user_info)?;
+    update_user_rewards(pool_info, user_info)?;
+    release_expired_locks(pool_info, user_info, now)?;
+    Ok(())
+}
+
+// Locks `shares` of the user's stake until `unlock_time`. No boost is given
+// if it would end before `now`.
+pub fn add_lock(
+    pool_info: &mut PoolInfo,
+    user_info: &mut UserInfo,
+    shares: Uint128,
+    boost_shares: Uint128,
+    unlock_time: u64,
+    now: u64,
+) -> Uint128 {
+    let end = boost_end(unlock_time);
+    let boost_shares = if end > now { boost_shares } else { Uint128::zero() };
+    if !boost_shares.is_zero() {
+        schedule_boost(&mut pool_info.state, end, boost_shares);
+    }
+    user_info.locks.push(StakeLock {
+        amount: shares,
+        boost_shares,
+        unlock_time,
+    });
+    reset_reward_debt(pool_info, user_info);
+    boost_shares
+}
+
+// -------------------------
+// Lock staked shares for a reward boost
+// -------------------------
+pub fn lock_stake(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    shares: Uint128,
+    duration: u64,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    let tier = config
+        .lock_tiers
+        .iter()
+        .find(|tier| tier.duration == duration)
+        .ok_or_else(|| StdError::generic_err("Unsupported lock duration"))?;
+
+    if shares.is_zero() {
+        return Err(StdError::generic_err("shares must be greater than zero"));
+    }
+
+    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+    let mut user_info = user_info_by_pool
+        .get(deps.storage, &info.sender)
+        .ok_or_else(|| StdError::generic_err("User info not found"))?;
+
+    let now = env.block.time.seconds();
+    settle_rewards(&mut pool_info, &mut user_info, now)?;
+
+    if user_info.amount_staked - locked_amount(&user_info) < shares {
+        return Err(StdError::generic_err("Insufficient unlocked staked amount"));
+    }
+
+    let boost_shares = shares.multiply_ratio(
+        tier.multiplier - Uint128::from(10000u32),
+        Uint128::from(10000u32),
+    );
+    let unlock_time = now + duration;
+    let boost_shares = add_lock(&mut pool_info, &mut user_info, shares, boost_shares, unlock_time, now);
+
+    user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "lock_stake")
+        .add_attribute("user", info.sender)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("shares", shares.to_string())
+        .add_attribute("boost_shares", boost_shares.to_string())
+        .add_attribute("unlock_time", unlock_time.to_string()))
+}
+
+// -------------------------
+// Release expired locks (permissionless). Boosts stop earning on their own;
+// this settles them and frees the pool's record of them.
+// -------------------------
+pub fn release_expired_locks_for(
+    deps: DepsMut,
+    env: Env,
+    pool: String,
+    user: String,
+) -> StdResult<Response> {
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let user_addr = deps.api.addr_validate(&user)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+
+    let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+    let mut user_info = user_info_by_pool
+        .get(deps.storage, &user_addr)
+        .ok_or_else(|| StdError::generic_err("User info not found"))?;
+
+    let boost_before = effective_shares(&user_info) - user_info.amount_staked;
+    settle_rewards(&mut pool_info, &mut user_info, env.block.time.seconds())?;
+    let released_boost = boost_before - (effective_shares(&user_info) - user_info.amount_staked);
+
+    user_info_by_pool.insert(deps.storage, &user_addr, &user_info)?;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "release_expired_locks")
+        .add_attribute("user", user_addr)
+        .add_attribute("pool", pool_addr)
+        .add_attribute("released_boost_shares", released_boost.to_string()))
+}
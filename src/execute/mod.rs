@@ -4,17 +4,26 @@ pub mod liquidity;
 pub mod rewards;
 pub mod pool;
 pub mod swap;
+pub mod lock;
+pub mod compound;
+pub mod incentives;
+pub mod gauge;
+pub mod vesting;
+pub mod viewing_key;
 
 
-pub use rewards::{update_user_rewards, pool_rewards_upkeep, handle_pool_rewards_update_reply};
+pub use rewards::{update_user_rewards, reward_debt_for, unscale, reset_reward_debt, accrue_rewards, reward_shares, effective_shares,
+    pool_rewards_upkeep, handle_pool_rewards_update_reply};
 pub use swap::{calculate_amm_swap};
+pub use pool::{handle_lp_token_instantiate_reply, ensure_pool_active};
 
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdResult, StdError, Uint128,
-    from_binary, Binary};
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdResult, StdError, Uint128, Uint256,
+    from_binary, Binary, Addr};
 use crate::msg::{ExecuteMsg, ReceiveMsg};
-use crate::state::{STATE, CONFIG, load_contracts};
+use crate::state::{STATE, CONFIG, AllocationReceived, load_contracts};
 
-pub const SCALING_FACTOR: Uint128 = Uint128::new(1_000_000);
+// Precision of reward accumulators and of reward debts
+pub const SCALING_FACTOR: Uint256 = Uint256::from_u128(1_000_000_000_000_000_000);
 
 pub fn execute_dispatch(
     deps: DepsMut, 
@@ -24,16 +33,48 @@ pub fn execute_dispatch(
 ) -> StdResult<Response> {
     match msg {
         ExecuteMsg::UpdateConfig { config } => update_config::update_config(deps, env, info, config),
-        ExecuteMsg::ClaimRewards {pools} => rewards::claim_rewards(deps, info, pools),
-        ExecuteMsg::AddLiquidity { amount_erth, amount_b, pool } =>
-            liquidity::add_liquidity(deps, env, info, amount_erth, amount_b, pool),
+        ExecuteMsg::ClaimRewards { pools, recipient } => rewards::claim_rewards(deps, env, info, pools, recipient),
+        ExecuteMsg::CompoundRewards { pools } => compound::compound_rewards(deps, env, info, pools),
+        ExecuteMsg::CompoundRewardsFor { user, pools } => compound::compound_rewards_for(deps, env, info, user, pools),
+        ExecuteMsg::SetAutoCompound { keeper } => compound::set_auto_compound(deps, info, keeper),
+        ExecuteMsg::ClaimVestedRewards {} => vesting::claim_vested_rewards(deps, env, info),
+        ExecuteMsg::ClaimUnvestedRewards {} => vesting::claim_unvested_rewards(deps, env, info),
+        ExecuteMsg::AddLiquidity { amount_erth, amount_b, pool, stake } =>
+            liquidity::add_liquidity(deps, env, info, amount_erth, amount_b, pool, stake.unwrap_or(true)),
         ExecuteMsg::RemoveLiquidity { pool, amount } => liquidity::remove_liquidity(deps, env, info, pool, amount),
+        ExecuteMsg::InstantRemoveLiquidity { pool, amount } =>
+            liquidity::instant_remove_liquidity(deps, env, info, pool, amount),
         ExecuteMsg::ClaimUnbondLiquidity { pool } => liquidity::claim_unbond_liquidity(deps, env, info, pool),
-        ExecuteMsg::AddPool {token, hash, symbol} => pool::add_pool(deps, env, info, token, hash, symbol),
+        ExecuteMsg::TransferPosition { pool, recipient, shares } =>
+            liquidity::transfer_position(deps, env, info, pool, recipient, shares),
+        ExecuteMsg::LockStake { pool, shares, duration } =>
+            lock::lock_stake(deps, env, info, pool, shares, duration),
+        ExecuteMsg::ReleaseExpiredLocks { pool, user } =>
+            lock::release_expired_locks_for(deps, env, pool, user),
+        ExecuteMsg::ClaimAllUnbonded {} => liquidity::claim_all_unbonded(deps, env, info),
+        ExecuteMsg::ClaimUnbondFor { pool, user } => liquidity::claim_unbond_for(deps, env, pool, user),
+        ExecuteMsg::SetAutoClaimUnbond { enabled } => liquidity::set_auto_claim_unbond(deps, info, enabled),
+        ExecuteMsg::CancelUnbond { pool, indices } => liquidity::cancel_unbond(deps, env, info, pool, indices),
+        ExecuteMsg::AddPool {token, hash, symbol, lp_token_code} =>
+            pool::add_pool(deps, env, info, token, hash, symbol, lp_token_code),
+        ExecuteMsg::CreateLpToken { pool, lp_token_code } =>
+            pool::create_lp_token(deps, env, info, pool, lp_token_code),
         ExecuteMsg::UpdatePoolConfig { pool, pool_config } => 
             pool::update_pool_config(deps, info, pool, pool_config),
+        ExecuteMsg::MigratePoolToken { pool, new_token, new_hash, token_migration_msg } =>
+            pool::migrate_pool_token(deps, env, info, pool, new_token, new_hash, token_migration_msg),
+        ExecuteMsg::ContinuePoolMigration { limit } => pool::continue_pool_migration(deps, env, info, limit),
+        ExecuteMsg::DeprecatePool { pool } => pool::deprecate_pool(deps, env, info, pool),
+        ExecuteMsg::RemovePool { pool } => pool::remove_pool(deps, env, info, pool),
+        ExecuteMsg::RegisterIncentiveToken { token, hash } =>
+            incentives::register_incentive_token(deps, env, info, token, hash),
+        ExecuteMsg::ClaimIncentives { pool, tokens } => incentives::claim_incentives(deps, env, info, pool, tokens),
+        ExecuteMsg::VoteGauges { votes } => gauge::vote_gauges(deps, env, info, votes),
+        ExecuteMsg::FinalizeGaugeEpoch { epoch, limit } => gauge::finalize_gauge_epoch(deps, env, epoch, limit),
+        ExecuteMsg::CreateViewingKey { entropy } => viewing_key::create_viewing_key(deps, env, info, entropy),
+        ExecuteMsg::SetViewingKey { key } => viewing_key::set_viewing_key_for_sender(deps, info, key),
         ExecuteMsg::UpdatePoolRewards {} =>
-            rewards::update_pool_rewards(deps, info),
+            rewards::update_pool_rewards(deps, env, info),
         ExecuteMsg::Receive { sender, from, amount, msg, memo: _ } => 
             recieve_dispatch(deps, env, info, sender, from, amount, msg),
     }
@@ -58,7 +99,11 @@ pub fn recieve_dispatch(
         ReceiveMsg::AnmlBuybackSwap {} => swap::anml_buyback_swap(deps, env, info, amount),
         ReceiveMsg::SwapToErthAndBurn {} => swap::swap_to_erth_and_burn(deps, env, info, amount),
         ReceiveMsg::SwapForGas {} => swap::swap_for_gas(deps, env, info, from_addr, amount),
-        ReceiveMsg::AllocationSend { allocation_id } => recieve_allocation(deps, env, info, amount, allocation_id),
+        ReceiveMsg::AllocationSend { allocation_id } => recieve_allocation(deps, env, info, from_addr, amount, allocation_id),
+        ReceiveMsg::StakeLp { pool } => liquidity::stake_lp(deps, env, info, from_addr, amount, pool),
+        ReceiveMsg::AddIncentive { pool, start_time, end_time } =>
+            incentives::add_incentive(deps, env, info, from_addr, amount, pool, start_time, end_time),
+        ReceiveMsg::RedeemLp { pool } => liquidity::redeem_lp(deps, env, info, from_addr, amount, pool),
     }
 }
 
@@ -66,8 +111,9 @@ fn recieve_allocation(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
+    from: Addr,
     amount: Uint128,
-    _allocation_id: u32,
+    allocation_id: u32,
 ) -> Result<Response, StdError> {
 
     // Load the state
@@ -79,10 +125,24 @@ fn recieve_allocation(
         return Err(StdError::generic_err("invalid token"));
     }
 
+    // Only allocations we claim, sent from the contract they are configured on
+    if !config
+        .allocation_sources
+        .iter()
+        .any(|source| source.allocation_id == allocation_id && source.contract.address == from)
+    {
+        return Err(StdError::generic_err("Unexpected allocation"));
+    }
+
     state.pending_reward += amount;
+    match state.allocations_received.iter_mut().find(|a| a.allocation_id == allocation_id) {
+        Some(received) => received.amount += amount,
+        None => state.allocations_received.push(AllocationReceived { allocation_id, amount }),
+    }
     STATE.save(deps.storage, &state)?;
 
     Ok(Response::new()
             .add_attribute("action", "claim_allocation")
+            .add_attribute("allocation_id", allocation_id.to_string())
             .add_attribute("claim_allocation_amount", amount.to_string()))
 }
\ No newline at end of file
@@ -1,9 +1,16 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, Uint128, to_binary,
-    CosmosMsg, StdResult, WasmMsg, };
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, Uint128, Uint256, to_binary,
+    CosmosMsg, StdResult, WasmMsg, SubMsg, SubMsgResult, Reply, Addr, Binary, Storage};
 use secret_toolkit::snip20;
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
-use crate::state::{CONFIG, PoolInfo, POOL_INFO,
-    PoolConfig, PoolState,};
+use crate::execute::rewards::end_reward_stream;
+use crate::execute::gauge::{migrate_gauge_votes, migrate_gauge_weights};
+use crate::msg::{LpTokenCode, Snip20InstantiateMsg, InitConfig};
+use crate::state::{CONFIG, STATE, PoolInfo, POOL_INFO, USER_INFO, load_contracts,
+    PoolConfig, PoolState, ContractInfo, PENDING_POOL, PendingPool, LateClaimPolicy,
+    UNBONDING_REQUESTS, USER_POOLS, POOL_MIGRATION, PoolMigration, PoolMigrationStage,
+    GAUGE_VOTES, GAUGE_EPOCH_VOTES,};
+use crate::LP_TOKEN_INSTANTIATE_REPLY_ID;
 
 
 
@@ -14,6 +21,7 @@ pub fn add_pool(
     token: String,
     hash: String,
     symbol: String,
+    lp_token_code: Option<LpTokenCode>,
 ) -> StdResult<Response> {
 
     // Ensure only the contract manager can add a pool
@@ -28,23 +36,43 @@ pub fn add_pool(
     if POOL_INFO.get(deps.storage, &pool_addr).is_some() {
         return Err(StdError::generic_err("Pool already exists"));
     }
+    // A migrating pool is out of POOL_INFO until it finishes
+    if let Some(migration) = POOL_MIGRATION.may_load(deps.storage)? {
+        if migration.old_pool == pool_addr || migration.new_pool == pool_addr {
+            return Err(StdError::generic_err("Pool is being migrated"));
+        }
+    }
 
     // Initialize a new PoolInfo
     let pool_state = PoolState {
         total_shares: Uint128::zero(),
-        reward_per_token_scaled: Uint128::zero(),
+        reward_per_token_scaled: Uint256::zero(),
         erth_reserve: Uint128::zero(),
         token_b_reserve: Uint128::zero(),
-        daily_rewards: [Uint128::zero(); 7],
-        daily_volumes: [Uint128::zero(); 7],
-        last_updated_day: 0,
+        reward_history: vec![],
+        volume_history: vec![],
+        history_start: 0,
         unbonding_shares: Uint128::zero(),
+        staked_shares: Uint128::zero(),
+        lp_token_supply: Uint128::zero(),
+        boosted_shares: Uint128::zero(),
+        boost_expiries: vec![],
+        deprecated: false,
+        incentives: vec![],
+        reward_rate: Uint256::zero(),
+        period_finish: 0,
+        last_reward_update: 0,
+        undistributed_rewards: Uint128::zero(),
     };
 
     let pool_config = PoolConfig {
         token_b_contract: pool_addr.clone(),
         token_b_hash: hash.clone(),
         token_b_symbol: symbol.clone(),
+        lp_token: None,
+        unbonding_seconds: None,
+        unbonding_window: None,
+        late_claim_policy: LateClaimPolicy::AutoRestake,
     };
 
     let pool_info = PoolInfo {
@@ -68,12 +96,151 @@ pub fn add_pool(
     // Save the new PoolInfo in POOL_INFO using the pool address as the key
     POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
 
-    Ok(Response::new()
+    let mut response = Response::new()
         .add_message(register_msg)
         .add_attribute("action", "add_pool")
+        .add_attribute("pool_address", pool_addr.to_string());
+
+    // Optionally instantiate an LP token for the pool
+    if let Some(lp_token_code) = lp_token_code {
+        let sub_msg = instantiate_lp_token(deps, &env, &pool_addr, &symbol, lp_token_code)?;
+        response = response.add_submessage(sub_msg);
+    }
+
+    Ok(response)
+}
+
+pub fn create_lp_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    lp_token_code: LpTokenCode,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+
+    if pool_info.config.lp_token.is_some() {
+        return Err(StdError::generic_err("Pool already has an LP token"));
+    }
+
+    let symbol = pool_info.config.token_b_symbol;
+    let sub_msg = instantiate_lp_token(deps, &env, &pool_addr, &symbol, lp_token_code)?;
+
+    Ok(Response::new()
+        .add_submessage(sub_msg)
+        .add_attribute("action", "create_lp_token")
         .add_attribute("pool_address", pool_addr.to_string()))
 }
 
+// Builds the SNIP-20 instantiate submessage; the contract is the admin and only minter
+fn instantiate_lp_token(
+    deps: DepsMut,
+    env: &Env,
+    pool_addr: &Addr,
+    symbol: &str,
+    lp_token_code: LpTokenCode,
+) -> StdResult<SubMsg> {
+    PENDING_POOL.save(deps.storage, &PendingPool {
+        pool: pool_addr.clone(),
+        lp_token_hash: lp_token_code.code_hash.clone(),
+    })?;
+
+    // SNIP-20 symbols are uppercase letters only
+    let mut lp_symbol: String = symbol
+        .chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .take(18)
+        .collect();
+    lp_symbol.push_str("LP");
+
+    let instantiate_msg = Snip20InstantiateMsg {
+        name: format!("ERTH-{} LP", symbol),
+        admin: Some(env.contract.address.to_string()),
+        symbol: lp_symbol,
+        decimals: 6,
+        initial_balances: None,
+        prng_seed: Binary::from(format!("{}{}", pool_addr, env.block.time.nanos()).as_bytes()),
+        config: Some(InitConfig {
+            public_total_supply: Some(true),
+            enable_deposit: Some(false),
+            enable_redeem: Some(false),
+            enable_mint: Some(true),
+            enable_burn: Some(true),
+            can_modify_denoms: Some(false),
+        }),
+        supported_denoms: None,
+    };
+
+    let instantiate = CosmosMsg::Wasm(WasmMsg::Instantiate {
+        admin: None,
+        code_id: lp_token_code.code_id,
+        code_hash: lp_token_code.code_hash,
+        msg: to_binary(&instantiate_msg)?,
+        funds: vec![],
+        label: format!("{}-{}-lp", env.contract.address, pool_addr),
+    });
+
+    Ok(SubMsg::reply_on_success(instantiate, LP_TOKEN_INSTANTIATE_REPLY_ID))
+}
+
+pub fn handle_lp_token_instantiate_reply(
+    deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> StdResult<Response> {
+    let pending = PENDING_POOL.load(deps.storage)?;
+    PENDING_POOL.remove(deps.storage);
+
+    let result = match msg.result {
+        SubMsgResult::Ok(result) => result,
+        SubMsgResult::Err(err) => return Err(StdError::generic_err(err)),
+    };
+
+    let lp_token_addr = result
+        .events
+        .iter()
+        .flat_map(|event| event.attributes.iter())
+        .find(|attr| attr.key == "contract_address")
+        .map(|attr| attr.value.clone())
+        .ok_or_else(|| StdError::generic_err("LP token address not found in reply"))?;
+    let lp_token_addr = deps.api.addr_validate(&lp_token_addr)?;
+
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pending.pool)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    pool_info.config.lp_token = Some(ContractInfo {
+        address: lp_token_addr.clone(),
+        code_hash: pending.lp_token_hash.clone(),
+    });
+    POOL_INFO.insert(deps.storage, &pending.pool, &pool_info)?;
+
+    // Register so LP tokens can be sent back in to stake or redeem
+    let register_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: lp_token_addr.to_string(),
+        code_hash: pending.lp_token_hash,
+        msg: to_binary(&snip20::HandleMsg::RegisterReceive {
+            code_hash: env.contract.code_hash,
+            padding: None,
+        })?,
+        funds: vec![],
+    });
+
+    Ok(Response::new()
+        .add_message(register_msg)
+        .add_attribute("action", "lp_token_instantiated")
+        .add_attribute("pool_address", pending.pool.to_string())
+        .add_attribute("lp_token_address", lp_token_addr.to_string()))
+}
+
 pub fn update_pool_config(
     deps: DepsMut,
     info: MessageInfo,
@@ -93,8 +260,21 @@ pub fn update_pool_config(
         .get(deps.storage, &pool_addr)
         .ok_or_else(|| StdError::generic_err("Pool not found"))?;
 
-    // Update the config
-    pool_info.config = pool_config;
+    // Changing the token would leave the pool keyed by the old address
+    if pool_config.token_b_contract != pool_info.config.token_b_contract {
+        return Err(StdError::generic_err("Use migrate_pool_token to change a pool's token"));
+    }
+
+    if let LateClaimPolicy::LatePenalty { penalty } = pool_config.late_claim_policy {
+        if penalty > Uint128::from(10000u32) {
+            return Err(StdError::generic_err("Late claim penalty cannot exceed 10000 basis points"));
+        }
+    }
+
+    // Update the config. The LP token is only ever set by its instantiate
+    // reply, so whatever was sent for it is ignored.
+    let lp_token = pool_info.config.lp_token.take();
+    pool_info.config = PoolConfig { lp_token, ..pool_config };
 
     // Save the updated PoolInfo back to storage
     POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
@@ -104,4 +284,381 @@ pub fn update_pool_config(
         .add_attribute("pool_address", pool_addr.to_string()))
 }
 
+// Moves a pool to the upgraded contract of its SNIP-20. The pool and every
+// per-pool record are re-keyed to the new address. Reserves carry over 1:1,
+// so any conversion message must swap the old balance for the same amount.
+pub fn migrate_pool_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+    new_token: String,
+    new_hash: String,
+    token_migration_msg: Option<Binary>,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    if POOL_MIGRATION.may_load(deps.storage)?.is_some() {
+        return Err(StdError::generic_err("A pool migration is already running"));
+    }
+
+    let old_addr = deps.api.addr_validate(&pool)?;
+    let new_addr = deps.api.addr_validate(&new_token)?;
+    if POOL_INFO.get(deps.storage, &new_addr).is_some() {
+        return Err(StdError::generic_err("Pool already exists"));
+    }
+    let pool_info = POOL_INFO
+        .get(deps.storage, &old_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    if let Some(pending) = PENDING_POOL.may_load(deps.storage)? {
+        if pending.pool == old_addr {
+            return Err(StdError::generic_err("LP token instantiation is still pending"));
+        }
+    }
+    let old_hash = pool_info.config.token_b_hash.clone();
+
+    // The pool is frozen until ContinuePoolMigration has moved all its records
+    let viewing_key = ViewingKey::create(
+        deps.storage,
+        &info,
+        &env,
+        env.contract.address.as_str(),
+        new_addr.as_bytes(),
+    );
+    POOL_INFO.remove(deps.storage, &old_addr)?;
+    POOL_MIGRATION.save(deps.storage, &PoolMigration {
+        old_pool: old_addr.clone(),
+        new_pool: new_addr.clone(),
+        new_hash: new_hash.clone(),
+        pool_info,
+        epoch: env.block.time.seconds() / config.gauge_epoch_seconds,
+        stage: PoolMigrationStage::Stakers,
+        cursor: 0,
+        users_migrated: 0,
+        viewing_key: viewing_key.clone(),
+    })?;
 
+    let mut messages = vec![];
+    if let Some(msg) = token_migration_msg {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: old_addr.to_string(),
+            code_hash: old_hash,
+            msg,
+            funds: vec![],
+        }));
+    }
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: new_addr.to_string(),
+        code_hash: new_hash.clone(),
+        msg: to_binary(&snip20::HandleMsg::RegisterReceive {
+            code_hash: env.contract.code_hash,
+            padding: None,
+        })?,
+        funds: vec![],
+    }));
+    messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: new_addr.to_string(),
+        code_hash: new_hash,
+        msg: to_binary(&snip20::HandleMsg::SetViewingKey {
+            key: viewing_key,
+            padding: None,
+        })?,
+        funds: vec![],
+    }));
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "migrate_pool_token")
+        .add_attribute("old_pool_address", old_addr.to_string())
+        .add_attribute("pool_address", new_addr.to_string()))
+}
+
+// Records moved per ContinuePoolMigration call
+const DEFAULT_MIGRATION_LIMIT: u32 = 50;
+const MAX_MIGRATION_LIMIT: u32 = 200;
+
+fn rename_user_pool(storage: &mut dyn Storage, user: &Addr, old_pool: &Addr, new_pool: &Addr) -> StdResult<()> {
+    let pools: Vec<Addr> = USER_POOLS
+        .get(storage, user)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|addr| if addr == *old_pool { new_pool.clone() } else { addr })
+        .collect();
+    USER_POOLS.insert(storage, user, &pools)
+}
+
+// Moves up to `limit` of a migrating pool's records to its new address.
+// Returns true once every record has moved.
+fn migrate_pool_records(storage: &mut dyn Storage, migration: &mut PoolMigration, limit: usize) -> StdResult<bool> {
+    let old_addr = migration.old_pool.clone();
+    let new_addr = migration.new_pool.clone();
+    let mut budget = limit;
+
+    while budget > 0 {
+        match migration.stage {
+            PoolMigrationStage::Stakers => {
+                let old_users = USER_INFO.add_suffix(old_addr.as_bytes());
+                let new_users = USER_INFO.add_suffix(new_addr.as_bytes());
+                let mut users = vec![];
+                for item in old_users.iter(storage)?.take(budget) {
+                    users.push(item?);
+                }
+                if users.is_empty() {
+                    migration.stage = PoolMigrationStage::Unbonding;
+                    continue;
+                }
+                budget -= users.len();
+                for (user, user_info) in users {
+                    new_users.insert(storage, &user, &user_info)?;
+                    old_users.remove(storage, &user)?;
+                    rename_user_pool(storage, &user, &old_addr, &new_addr)?;
+                    migration.users_migrated += 1;
+                }
+            }
+            PoolMigrationStage::Unbonding => {
+                // Unbond requests also carry the pool address
+                let old_unbonding = UNBONDING_REQUESTS.add_suffix(old_addr.as_bytes());
+                let new_unbonding = UNBONDING_REQUESTS.add_suffix(new_addr.as_bytes());
+                let mut requests = vec![];
+                for item in old_unbonding.iter(storage)?.take(budget) {
+                    requests.push(item?);
+                }
+                if requests.is_empty() {
+                    migration.stage = PoolMigrationStage::GaugeVotes;
+                    migration.cursor = 0;
+                    continue;
+                }
+                budget -= requests.len();
+                for (user, mut records) in requests {
+                    for record in records.iter_mut() {
+                        record.pool = new_addr.clone();
+                    }
+                    new_unbonding.insert(storage, &user, &records)?;
+                    old_unbonding.remove(storage, &user)?;
+                    // Stakers were already counted and re-indexed
+                    if !USER_INFO.add_suffix(new_addr.as_bytes()).contains(storage, &user) {
+                        rename_user_pool(storage, &user, &old_addr, &new_addr)?;
+                        migration.users_migrated += 1;
+                    }
+                }
+            }
+            PoolMigrationStage::GaugeVotes => {
+                let read = migrate_gauge_votes(storage, &GAUGE_VOTES, &old_addr, &new_addr, migration.cursor, budget)?;
+                if read == 0 {
+                    migration.stage = PoolMigrationStage::EpochVotes(migration.epoch);
+                    migration.cursor = 0;
+                    continue;
+                }
+                budget -= read;
+                migration.cursor += read as u32;
+            }
+            PoolMigrationStage::EpochVotes(epoch) => {
+                // Only the running and next epoch's votes can still count
+                let epoch_votes = GAUGE_EPOCH_VOTES.add_suffix(&epoch.to_be_bytes());
+                let read = migrate_gauge_votes(storage, &epoch_votes, &old_addr, &new_addr, migration.cursor, budget)?;
+                if read == 0 {
+                    if epoch > migration.epoch {
+                        return Ok(true);
+                    }
+                    migration.stage = PoolMigrationStage::EpochVotes(epoch + 1);
+                    migration.cursor = 0;
+                    continue;
+                }
+                budget -= read;
+                migration.cursor += read as u32;
+            }
+        }
+    }
+    Ok(false)
+}
+
+// -------------------------
+// Move the next page of a migrating pool's records. Once all have moved, the
+// pool's token reserve is set to what the new token actually holds, since a
+// token migration need not convert 1:1, and the pool is unfrozen.
+// -------------------------
+pub fn continue_pool_migration(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    limit: Option<u32>,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    let mut migration = POOL_MIGRATION
+        .may_load(deps.storage)?
+        .ok_or_else(|| StdError::generic_err("No pool migration is running"))?;
+
+    let limit = limit.unwrap_or(DEFAULT_MIGRATION_LIMIT).min(MAX_MIGRATION_LIMIT) as usize;
+    if !migrate_pool_records(deps.storage, &mut migration, limit)? {
+        POOL_MIGRATION.save(deps.storage, &migration)?;
+        return Ok(Response::new()
+            .add_attribute("action", "continue_pool_migration")
+            .add_attribute("pool_address", migration.new_pool.to_string())
+            .add_attribute("users_migrated", migration.users_migrated.to_string())
+            .add_attribute("finished", "false"));
+    }
+
+    let balance = snip20::balance_query(
+        deps.querier,
+        env.contract.address.to_string(),
+        migration.viewing_key.clone(),
+        256,
+        migration.new_hash.clone(),
+        migration.new_pool.to_string(),
+    )?
+    .amount;
+    let mut pool_info = migration.pool_info;
+    let old_reserve = pool_info.state.token_b_reserve;
+    if balance.is_zero() && !old_reserve.is_zero() {
+        return Err(StdError::generic_err("The new token holds none of the pool's reserve"));
+    }
+    pool_info.state.token_b_reserve = balance;
+
+    migrate_gauge_weights(deps.storage, &migration.old_pool, &migration.new_pool, migration.epoch)?;
+    pool_info.config.token_b_contract = migration.new_pool.clone();
+    pool_info.config.token_b_hash = migration.new_hash;
+    POOL_INFO.insert(deps.storage, &migration.new_pool, &pool_info)?;
+    POOL_MIGRATION.remove(deps.storage);
+
+    Ok(Response::new()
+        .add_attribute("action", "continue_pool_migration")
+        .add_attribute("old_pool_address", migration.old_pool.to_string())
+        .add_attribute("pool_address", migration.new_pool.to_string())
+        .add_attribute("users_migrated", migration.users_migrated.to_string())
+        .add_attribute("old_token_b_reserve", old_reserve.to_string())
+        .add_attribute("token_b_reserve", balance.to_string())
+        .add_attribute("finished", "true"))
+}
+
+pub fn ensure_pool_active(pool_info: &PoolInfo) -> StdResult<()> {
+    if pool_info.state.deprecated {
+        return Err(StdError::generic_err("Pool is deprecated"));
+    }
+    Ok(())
+}
+
+pub fn deprecate_pool(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+    ensure_pool_active(&pool_info)?;
+
+    // The rest of the pool's stream goes back into the held-back rewards
+    let mut state = STATE.load(deps.storage)?;
+    end_reward_stream(&mut state, &mut pool_info, env.block.time.seconds())?;
+    STATE.save(deps.storage, &state)?;
+
+    pool_info.state.deprecated = true;
+    POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "deprecate_pool")
+        .add_attribute("pool_address", pool_addr.to_string()))
+}
+
+// Deletes a deprecated pool once every LP has left. Whatever is left in the
+// reserves (locked minimum liquidity and rounding dust) is burned or sent to
+// the contract manager.
+pub fn remove_pool(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool: String,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.contract_manager {
+        return Err(StdError::generic_err("unauthorized"));
+    }
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+
+    let pool_addr = deps.api.addr_validate(&pool)?;
+    let mut pool_info = POOL_INFO
+        .get(deps.storage, &pool_addr)
+        .ok_or_else(|| StdError::generic_err("Pool not found"))?;
+
+    if !pool_info.state.deprecated {
+        return Err(StdError::generic_err("Pool must be deprecated first"));
+    }
+    // Entries left with only unclaimed incentives can still be claimed
+    // after removal, so just the staked shares count here
+    if !pool_info.state.staked_shares.is_zero()
+        || !pool_info.state.unbonding_shares.is_zero()
+        || !pool_info.state.lp_token_supply.is_zero()
+    {
+        return Err(StdError::generic_err("Pool still has liquidity providers"));
+    }
+    let now = env.block.time.seconds();
+    if pool_info.state.incentives.iter().flat_map(|i| i.streams.iter()).any(|s| s.end_time > now) {
+        return Err(StdError::generic_err("Pool still has running incentives"));
+    }
+    let mut state = STATE.load(deps.storage)?;
+    end_reward_stream(&mut state, &mut pool_info, now)?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    // Incentives streamed while no one was staked go back to the manager
+    for incentive in pool_info.state.incentives.iter().filter(|i| !i.undistributed.is_zero()) {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: incentive.token.address.to_string(),
+            code_hash: incentive.token.code_hash.clone(),
+            msg: to_binary(&snip20::HandleMsg::Transfer {
+                recipient: config.contract_manager.to_string(),
+                amount: incentive.undistributed,
+                padding: None,
+                memo: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+    if !pool_info.state.erth_reserve.is_zero() {
+        state.erth_burned += pool_info.state.erth_reserve;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addrs.erth_token.address.to_string(),
+            code_hash: addrs.erth_token.code_hash.clone(),
+            msg: to_binary(&snip20::HandleMsg::Burn {
+                amount: pool_info.state.erth_reserve,
+                memo: None,
+                padding: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+    if !pool_info.state.token_b_reserve.is_zero() {
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: pool_info.config.token_b_contract.to_string(),
+            code_hash: pool_info.config.token_b_hash.clone(),
+            msg: to_binary(&snip20::HandleMsg::Transfer {
+                recipient: config.contract_manager.to_string(),
+                amount: pool_info.state.token_b_reserve,
+                padding: None,
+                memo: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+
+    STATE.save(deps.storage, &state)?;
+    POOL_INFO.remove(deps.storage, &pool_addr)?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "remove_pool")
+        .add_attribute("pool_address", pool_addr.to_string())
+        .add_attribute("erth_burned", pool_info.state.erth_reserve.to_string())
+        .add_attribute("token_b_returned", pool_info.state.token_b_reserve.to_string()))
+}
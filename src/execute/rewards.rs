@@ -1,80 +1,247 @@
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdResult, StdError, Uint128, to_binary,
+use cosmwasm_std::{Addr, DepsMut, Env, MessageInfo, Response, StdResult, StdError, Uint128, Uint256, to_binary,
     CosmosMsg, WasmMsg, SubMsg};
 use secret_toolkit::snip20;
 
-use crate::state::{CONFIG, STATE, PoolInfo, POOL_INFO, UserInfo, USER_INFO, State, load_contracts};
+use crate::state::{Config, CONFIG, STATE, PoolInfo, POOL_INFO, PoolState, UserInfo, USER_INFO, State, load_contracts,
+    USER_POOLS, PENDING_UPKEEP_CALLER, ContractInfo, prune_user_pools, RewardCheckpoint, UserIncentive,
+    GAUGE_WEIGHTS, GAUGE_FINALIZED};
+use crate::execute::incentives::{accrue_incentives, settle_user_incentives, reset_incentive_debts,
+    take_pending_incentives, incentive_payout_messages, has_pending_incentives};
 use crate::msg::{SendMsg};
 use crate::execute::SCALING_FACTOR;
+use crate::execute::vesting::vest_rewards;
+use crate::execute::lock::settle_rewards;
 use crate::POOL_REWARDS_UPDATE_REPLY_ID;
 
 
 pub fn claim_rewards(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    pools: Vec<String>,
+    pools: Option<Vec<String>>,
+    recipient: Option<String>,
 ) -> StdResult<Response> {
     let state = STATE.load(deps.storage)?;
     let config = CONFIG.load(deps.storage)?;
     let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender.clone(),
+    };
     let mut total_rewards = Uint128::zero();
-
-    for pool_addr_str in pools.iter() {
-        let pool_addr = deps.api.addr_validate(pool_addr_str)?;
-
-        let pool_info = POOL_INFO
-            .get(deps.storage, &pool_addr)
-            .ok_or_else(|| StdError::generic_err("Pool info not found"))?;
-        let user_info_by_pool = USER_INFO.add_suffix(&pool_addr.as_bytes());
-
-        let mut user_info = user_info_by_pool
-            .get(deps.storage, &info.sender)
-            .ok_or_else(|| StdError::generic_err("User info not found"))?;
-
-        update_user_rewards(&pool_info, &mut user_info)?;
+    let mut incentive_payouts = vec![];
+
+    // Without a list, claim from every pool the sender has a position in
+    let pool_addrs = match pools {
+        Some(pools) => pools
+            .iter()
+            .map(|pool| deps.api.addr_validate(pool))
+            .collect::<StdResult<Vec<_>>>()?,
+        None => {
+            prune_user_pools(deps.storage, &info.sender)?;
+            USER_POOLS.get(deps.storage, &info.sender).unwrap_or_default()
+        }
+    };
+
+    let mut pools_claimed = 0u32;
+    for pool_addr in pool_addrs {
+        // Pools without a position are skipped rather than failing the claim
+        let mut pool_info = match POOL_INFO.get(deps.storage, &pool_addr) {
+            Some(pool_info) => pool_info,
+            None => continue,
+        };
+        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+        let mut user_info = match user_info_by_pool.get(deps.storage, &info.sender) {
+            Some(user_info) => user_info,
+            None => continue,
+        };
+        accrue_rewards(&mut pool_info, env.block.time.seconds())?;
+        pools_claimed += 1;
+
+        settle_rewards(&mut pool_info, &mut user_info, env.block.time.seconds())?;
 
         let amount_to_claim = user_info.pending_rewards;
         total_rewards += amount_to_claim;
         user_info.pending_rewards = Uint128::zero();
+        incentive_payouts.extend(take_pending_incentives(deps.storage, &mut user_info, None));
 
-        user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
+        // Left over from a withdrawal only to hold incentives, now paid out
+        if user_info.amount_staked.is_zero() && !has_pending_incentives(&user_info) {
+            user_info_by_pool.remove(deps.storage, &info.sender)?;
+        } else {
+            user_info_by_pool.insert(deps.storage, &info.sender, &user_info)?;
+        }
         POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
     }
 
     STATE.save(deps.storage, &state)?;
 
+    // With vesting on, ERTH goes onto the recipient's schedule and only what
+    // has already vested is paid out
+    let payout = if config.reward_vesting_seconds > 0 {
+        vest_rewards(deps.storage, &recipient, total_rewards, env.block.time.seconds(), config.reward_vesting_seconds)?
+    } else {
+        total_rewards
+    };
+
     let mut messages: Vec<CosmosMsg> = Vec::new();
 
-    if !total_rewards.is_zero() {
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: addrs.erth_token.address.to_string(),
-            code_hash: addrs.erth_token.code_hash.clone(),
-            msg: to_binary(&snip20::HandleMsg::Transfer {
-                recipient: info.sender.to_string(),
-                amount: total_rewards,
-                padding: None,
-                memo: None,
-            })?,
-            funds: vec![],
-        }));
-    }
-
-    let allocation_claim_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: addrs.staking.address.to_string(),
-        code_hash: addrs.staking.code_hash.clone(),
-        msg: to_binary(&SendMsg::ClaimAllocation {
-            allocation_id: 1,
-        })?,
-        funds: vec![],
-    });
+    if !payout.is_zero() {
+        messages.push(erth_transfer_msg(&addrs.erth_token, &recipient, payout)?);
+    }
+    messages.extend(incentive_payout_messages(&recipient, incentive_payouts)?);
 
     Ok(Response::new()
         .add_attribute("action", "claim_rewards_and_allocation_multi")
+        .add_attribute("recipient", recipient)
+        .add_attribute("pools_claimed", pools_claimed.to_string())
         .add_attribute("total_claimed", total_rewards.to_string())
+        .add_attribute("paid_out", payout.to_string())
         .add_messages(messages)
-        .add_message(allocation_claim_msg))
+        .add_messages(allocation_claim_msgs(&config)?))
 }
 
+// One ClaimAllocation per configured source
+fn allocation_claim_msgs(config: &Config) -> StdResult<Vec<CosmosMsg>> {
+    config
+        .allocation_sources
+        .iter()
+        .map(|source| {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: source.contract.address.to_string(),
+                code_hash: source.contract.code_hash.clone(),
+                msg: to_binary(&SendMsg::ClaimAllocation {
+                    allocation_id: source.allocation_id,
+                })?,
+                funds: vec![],
+            }))
+        })
+        .collect()
+}
+
+
+
+// Staked shares plus the boost from time locks
+pub fn effective_shares(user_info: &UserInfo) -> Uint128 {
+    let boost: Uint128 = user_info.locks.iter().map(|lock| lock.boost_shares).sum();
+    user_info.amount_staked + boost
+}
 
+// Shares that reward accumulators are divided over. Unbonding, LP token and
+// minimum liquidity shares are left out since nobody can claim on them.
+pub fn reward_shares(state: &PoolState) -> Uint128 {
+    state.staked_shares + state.boosted_shares
+}
+
+pub fn reward_debt_for(pool_info: &PoolInfo, user_info: &UserInfo) -> Uint256 {
+    Uint256::from(effective_shares(user_info)) * pool_info.state.reward_per_token_scaled
+}
+
+// Token amount of a scaled reward value, rounded down
+pub fn unscale(scaled: Uint256) -> StdResult<Uint128> {
+    Ok(Uint128::try_from(scaled / SCALING_FACTOR)?)
+}
+
+// Streams ERTH emissions and incentives into the pool's accumulators up to
+// `now`. Call before anything that reads rewards or changes shares.
+pub fn accrue_rewards(pool_info: &mut PoolInfo, now: u64) -> StdResult<()> {
+    // Boosts that ran out stop earning exactly when they end
+    while let Some(index) = pool_info
+        .state
+        .boost_expiries
+        .iter()
+        .position(|expiry| expiry.checkpoint.is_none() && expiry.time <= now)
+    {
+        let time = pool_info.state.boost_expiries[index].time;
+        accrue_until(pool_info, time)?;
+        let checkpoint = RewardCheckpoint {
+            reward_per_token_scaled: pool_info.state.reward_per_token_scaled,
+            incentives: pool_info
+                .state
+                .incentives
+                .iter()
+                .map(|incentive| (incentive.token.address.clone(), incentive.reward_per_token_scaled))
+                .collect(),
+        };
+        let expiry = &mut pool_info.state.boost_expiries[index];
+        expiry.checkpoint = Some(checkpoint);
+        pool_info.state.boosted_shares = pool_info.state.boosted_shares.checked_sub(expiry.boost_shares)?;
+    }
+    accrue_until(pool_info, now)
+}
+
+fn accrue_until(pool_info: &mut PoolInfo, now: u64) -> StdResult<()> {
+    accrue_incentives(pool_info, now);
+
+    let divisor = reward_shares(&pool_info.state);
+    let until = now.min(pool_info.state.period_finish);
+    if until > pool_info.state.last_reward_update {
+        let emitted_scaled = pool_info.state.reward_rate * Uint256::from(until - pool_info.state.last_reward_update);
+        if divisor.is_zero() {
+            // No one holds shares, so the emission is set aside rather than
+            // left for whoever stakes next
+            pool_info.state.undistributed_rewards += unscale(emitted_scaled)?;
+        } else {
+            pool_info.state.reward_per_token_scaled += emitted_scaled / Uint256::from(divisor);
+        }
+    }
+    pool_info.state.last_reward_update = pool_info.state.last_reward_update.max(now);
+    Ok(())
+}
+
+// Stops the pool's ERTH stream at `now`. What it has not paid out, and what
+// it set aside while nothing was staked, goes back to the held-back rewards.
+pub fn end_reward_stream(state: &mut State, pool_info: &mut PoolInfo, now: u64) -> StdResult<()> {
+    accrue_rewards(pool_info, now)?;
+    let unstreamed = if pool_info.state.period_finish > pool_info.state.last_reward_update {
+        unscale(pool_info.state.reward_rate
+            * Uint256::from(pool_info.state.period_finish - pool_info.state.last_reward_update))?
+    } else {
+        Uint128::zero()
+    };
+    state.undistributed_rewards += unstreamed + pool_info.state.undistributed_rewards;
+    pool_info.state.undistributed_rewards = Uint128::zero();
+    pool_info.state.reward_rate = Uint256::zero();
+    pool_info.state.period_finish = now;
+    Ok(())
+}
+
+// Settles what `shares_before` earned up to a checkpoint and restarts the
+// debts there on `shares_after`
+pub fn settle_at_checkpoint(
+    checkpoint: &RewardCheckpoint,
+    user_info: &mut UserInfo,
+    shares_before: Uint128,
+    shares_after: Uint128,
+) -> StdResult<()> {
+    let earned = Uint256::from(shares_before) * checkpoint.reward_per_token_scaled;
+    user_info.pending_rewards += unscale(earned.checked_sub(user_info.reward_debt)?)?;
+    user_info.reward_debt = Uint256::from(shares_after) * checkpoint.reward_per_token_scaled;
+
+    for (token, reward_per_token_scaled) in checkpoint.incentives.iter() {
+        let earned = Uint256::from(shares_before) * *reward_per_token_scaled;
+        let debt = Uint256::from(shares_after) * *reward_per_token_scaled;
+        match user_info.incentives.iter_mut().find(|entry| entry.token == *token) {
+            Some(entry) => {
+                entry.pending_rewards += unscale(earned.checked_sub(entry.reward_debt)?)?;
+                entry.reward_debt = debt;
+            }
+            // Held these shares since before the token was added
+            None => user_info.incentives.push(UserIncentive {
+                token: token.clone(),
+                reward_debt: debt,
+                pending_rewards: unscale(earned)?,
+            }),
+        }
+    }
+    Ok(())
+}
+
+// Resets the ERTH and incentive debts after the user's shares change
+pub fn reset_reward_debt(pool_info: &PoolInfo, user_info: &mut UserInfo) {
+    user_info.reward_debt = reward_debt_for(pool_info, user_info);
+    reset_incentive_debts(pool_info, user_info, effective_shares(user_info));
+}
 
 pub fn update_user_rewards(
     pool_info: &PoolInfo,
@@ -82,124 +249,206 @@ pub fn update_user_rewards(
 ) -> StdResult<()> {
 
     // Calculate the pending rewards for the user
-    let pending_reward = (user_info.amount_staked * pool_info.state.reward_per_token_scaled) / SCALING_FACTOR - user_info.reward_debt;
+    let pending_reward = unscale(reward_debt_for(pool_info, user_info).checked_sub(user_info.reward_debt)?)?;
 
     // Update the user's pending rewards
     user_info.pending_rewards += pending_reward;
 
     // Update the user's reward debt to the current state of the pool
-    user_info.reward_debt = (user_info.amount_staked * pool_info.state.reward_per_token_scaled) / SCALING_FACTOR;
+    user_info.reward_debt = reward_debt_for(pool_info, user_info);
+
+    // Same for each external incentive token
+    settle_user_incentives(pool_info, user_info, effective_shares(user_info))?;
 
     Ok(())
 }
 
+// Splits pending_reward between the pools and starts their reward streams.
+// Returns the keeper bounty set aside for the caller, zero if nothing went out.
 pub fn pool_rewards_upkeep(
     deps: &mut DepsMut,
     env: Env,
     state: &mut State,
-) -> Result<(), StdError> {
+) -> Result<Uint128, StdError> {
+    let config = CONFIG.load(deps.storage)?;
+
+    // ERTH the pools set aside while no one was staked is held back as well
+    let mut swept = vec![];
+    for item in POOL_INFO.iter(deps.storage)? {
+        let (addr, mut pool_info) = item?;
+        if !pool_info.state.undistributed_rewards.is_zero() {
+            state.undistributed_rewards += pool_info.state.undistributed_rewards;
+            pool_info.state.undistributed_rewards = Uint128::zero();
+            swept.push((addr, pool_info));
+        }
+    }
+    for (addr, pool_info) in swept {
+        POOL_INFO.insert(deps.storage, &addr, &pool_info)?;
+    }
+
+    // Rewards held back earlier go out with this distribution unless a sink takes them
+    if config.undistributed_sink.is_none() {
+        state.pending_reward += state.undistributed_rewards;
+        state.undistributed_rewards = Uint128::zero();
+    }
     if state.pending_reward.is_zero() {
-        return Ok(());
+        return Ok(Uint128::zero());
     }
 
-    let current_day = env.block.time.seconds() / 86400;
+    let current_epoch = env.block.time.seconds() / config.gauge_epoch_seconds;
+    // Votes only count once their epoch has been finalized
+    let gauge_weights = if GAUGE_FINALIZED.contains(deps.storage, &current_epoch) {
+        GAUGE_WEIGHTS.get(deps.storage, &current_epoch).unwrap_or_default()
+    } else {
+        vec![]
+    };
     let mut pools_data = Vec::new();
     let mut total_volume = Uint128::zero();
+    let mut total_votes = Uint128::zero();
 
     // STEP 1: READ-ONLY PASS
     // Iterate through all pools to calculate the total volume based on their current, unmodified state.
-    let mut iter = POOL_INFO.iter(deps.storage)?;
-    while let Some(item) = iter.next() {
+    for item in POOL_INFO.iter(deps.storage)? {
         let (addr, pool_info) = item?;
-        let pool_volume: Uint128 = pool_info.state.daily_volumes.iter().cloned().sum();
+        // Deprecated pools stop earning but still roll their days over
+        let (pool_volume, pool_votes) = if pool_info.state.deprecated {
+            (Uint128::zero(), Uint128::zero())
+        } else {
+            let votes = gauge_weights
+                .iter()
+                .find(|w| w.pool == addr)
+                .map(|w| w.votes)
+                .unwrap_or_default();
+            (pool_info.state.weighted_volume(config.volume_decay), votes)
+        };
         total_volume += pool_volume;
-        pools_data.push((addr, pool_info, pool_volume));
+        total_votes += pool_votes;
+        pools_data.push((addr, pool_info, pool_volume, pool_votes));
     }
 
-    // If total volume from storage is zero, we can't distribute. Exit.
-    // This is now safe because we haven't wiped any data.
-    if total_volume.is_zero() {
-        return Ok(());
+    // If there is neither volume nor votes, we can't distribute. Hold the
+    // rewards back; no pool data has been touched yet.
+    if total_volume.is_zero() && total_votes.is_zero() {
+        state.undistributed_rewards += state.pending_reward;
+        state.pending_reward = Uint128::zero();
+        return Ok(Uint128::zero());
     }
 
+    let mut keeper_bounty = state.pending_reward.multiply_ratio(config.keeper_bounty, Uint128::from(10000u32));
+
+    // Split between gauge votes and volume. When one side is empty the other gets everything.
+    let reward_pool = state.pending_reward - keeper_bounty;
+    let vote_rewards = if total_votes.is_zero() {
+        Uint128::zero()
+    } else if total_volume.is_zero() {
+        reward_pool
+    } else {
+        reward_pool.multiply_ratio(config.gauge_vote_weight, Uint128::from(10000u32))
+    };
+    let volume_rewards = reward_pool - vote_rewards;
+    let pool_share_of = |pool_volume: Uint128, pool_votes: Uint128| {
+        let mut share = Uint128::zero();
+        if !total_volume.is_zero() {
+            share += pool_volume.multiply_ratio(volume_rewards, total_volume);
+        }
+        if !total_votes.is_zero() {
+            share += pool_votes.multiply_ratio(vote_rewards, total_votes);
+        }
+        share
+    };
+
     // STEP 2: DISTRIBUTE REWARDS (IN MEMORY)
     // The rewards are calculated based on the data we just read, BEFORE any time-bumping.
-    let reward_pool = state.pending_reward;
-    for (_addr, pool_info, pool_volume) in pools_data.iter_mut() {
-        if !pool_volume.is_zero() {
-            let pool_share = pool_volume.multiply_ratio(reward_pool, total_volume);
-            if !pool_info.state.total_shares.is_zero() {
-                // Your original math for calculating the increment
-                let increment = pool_share
-                    .saturating_mul(SCALING_FACTOR)
-                    .checked_div(pool_info.state.total_shares)
-                    .unwrap_or(Uint128::zero());
-                pool_info.state.reward_per_token_scaled += increment;
-            }
+    // Each pool's share is streamed over the next reward_stream_seconds together with
+    // whatever its current stream has left, so stakers only earn while they are staked.
+    let now = env.block.time.seconds();
+    let duration = config.reward_stream_seconds;
+    let mut pool_rewards = Vec::with_capacity(pools_data.len());
+    let mut allotted = Uint128::zero();
+    for (_addr, pool_info, pool_volume, pool_votes) in pools_data.iter_mut() {
+        let pool_share = pool_share_of(*pool_volume, *pool_votes);
+        allotted += pool_share;
+        if pool_share.is_zero() {
+            pool_rewards.push(Uint128::zero());
+            continue;
+        }
+        accrue_rewards(pool_info, now)?;
+        if reward_shares(&pool_info.state).is_zero() {
+            // No one to stream to: hold back the share along with whatever
+            // the current stream has not paid out
+            end_reward_stream(state, pool_info, now)?;
+            state.undistributed_rewards += pool_share;
+            pool_rewards.push(Uint128::zero());
+            continue;
         }
+        let remaining_scaled = if now < pool_info.state.period_finish {
+            pool_info.state.reward_rate * Uint256::from(pool_info.state.period_finish - now)
+        } else {
+            Uint256::zero()
+        };
+        pool_info.state.reward_rate =
+            (Uint256::from(pool_share) * SCALING_FACTOR + remaining_scaled) / Uint256::from(duration);
+        pool_info.state.period_finish = now + duration;
+        pool_info.state.last_reward_update = now;
+        pool_rewards.push(pool_share);
+    }
+    // Rounding dust from the split is held back too
+    state.undistributed_rewards += reward_pool - allotted;
+    // The keeper is only paid when some pool actually received rewards
+    if pool_rewards.iter().all(|reward| reward.is_zero()) {
+        state.undistributed_rewards += keeper_bounty;
+        keeper_bounty = Uint128::zero();
     }
     // Mark the global pending reward as distributed
     state.pending_reward = Uint128::zero();
 
 
-    // STEP 3: BUMP DAYS AND SAVE
+    // STEP 3: BUMP BUCKETS AND SAVE
     // Now, iterate through the pools again to apply time-based updates and save the final state.
-    for (addr, mut pool_info, pool_volume) in pools_data {
-        // --- YOUR ORIGINAL TIME-BUMPING LOGIC, UNCHANGED ---
-        let days_passed = current_day.saturating_sub(pool_info.state.last_updated_day);
-        if days_passed > 0 {
-            if days_passed >= 7 {
-                pool_info.state.daily_rewards = [Uint128::zero(); 7];
-                pool_info.state.daily_volumes = [Uint128::zero(); 7];
-            } else {
-                for _ in 0..days_passed {
-                    pool_info.state.daily_rewards.rotate_right(1);
-                    pool_info.state.daily_rewards[0] = Uint128::zero();
-                    pool_info.state.daily_volumes.rotate_right(1);
-                    pool_info.state.daily_volumes[0] = Uint128::zero();
-                }
-            }
-            pool_info.state.last_updated_day = current_day;
-        }
-        // --- END OF YOUR ORIGINAL LOGIC ---
+    for ((addr, mut pool_info, _, _), pool_reward) in pools_data.into_iter().zip(pool_rewards) {
+        pool_info
+            .state
+            .roll_history(now, config.volume_bucket_seconds, config.volume_window);
 
-        // Add the newly distributed share to the now-updated daily_rewards array
-        if !pool_volume.is_zero() {
-            let pool_share = pool_volume.multiply_ratio(reward_pool, total_volume);
-            pool_info.state.daily_rewards[0] += pool_share;
-        }
+        // Record what the pool's stakers will actually receive
+        pool_info.state.reward_history[0] += pool_reward;
 
         // Save the fully updated pool information to storage
         POOL_INFO.insert(deps.storage, &addr, &pool_info)?;
     }
 
-    Ok(())
+    Ok(keeper_bounty)
 }
 
 
 
 pub fn update_pool_rewards(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
 ) -> StdResult<Response> {
-    // Load config to get the allocation contract info
+    // Load config to get the allocation sources
     let config = CONFIG.load(deps.storage)?;
-    let addrs = load_contracts(&deps.as_ref(), &config)?;
 
-    // Submessage to claim allocation #1
-    let allocation_claim_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-        contract_addr: addrs.staking.address.to_string(),
-        code_hash: addrs.staking.code_hash.clone(),
-        msg: to_binary(&SendMsg::ClaimAllocation {
-            allocation_id: 1,
-        })?,
-        funds: vec![],
-    });
+    let mut state = STATE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    if now < state.last_upkeep + config.min_upkeep_interval {
+        return Err(StdError::generic_err("Pool rewards were updated too recently"));
+    }
+    state.last_upkeep = now;
+    STATE.save(deps.storage, &state)?;
+    PENDING_UPKEEP_CALLER.save(deps.storage, &info.sender)?;
+
+    let mut claim_msgs = allocation_claim_msgs(&config)?;
 
-    // Wrap as SubMsg, reply_on_success with an ID
-    let sub_msg = SubMsg::reply_on_success(allocation_claim_msg, POOL_REWARDS_UPDATE_REPLY_ID);
+    // Claims run in order, so replying to the last one distributes all of them
+    let last_claim = claim_msgs
+        .pop()
+        .ok_or_else(|| StdError::generic_err("No allocation sources configured"))?;
+    let sub_msg = SubMsg::reply_on_success(last_claim, POOL_REWARDS_UPDATE_REPLY_ID);
 
     Ok(Response::new()
+        .add_messages(claim_msgs)
         .add_submessage(sub_msg)
         .add_attribute("action", "manual_update_pool_rewards_initiated")
         .add_attribute("sender", info.sender))
@@ -207,7 +456,45 @@ pub fn update_pool_rewards(
 
 pub fn handle_pool_rewards_update_reply(mut deps: DepsMut, env: Env) -> StdResult<Response> {
     let mut state = STATE.load(deps.storage)?;
-    pool_rewards_upkeep(&mut deps, env, &mut state)?;
+    let config = CONFIG.load(deps.storage)?;
+    let caller = PENDING_UPKEEP_CALLER.load(deps.storage)?;
+    PENDING_UPKEEP_CALLER.remove(deps.storage);
+
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let bounty = pool_rewards_upkeep(&mut deps, env, &mut state)?;
+
+    let mut response = Response::new().add_attribute("action", "manual_update_pool_rewards_complete");
+    if !bounty.is_zero() {
+        response = response
+            .add_message(erth_transfer_msg(&addrs.erth_token, &caller, bounty)?)
+            .add_attribute("keeper", caller)
+            .add_attribute("keeper_bounty", bounty.to_string());
+    }
+    if let Some(sink) = &config.undistributed_sink {
+        if !state.undistributed_rewards.is_zero() {
+            let amount = state.undistributed_rewards;
+            state.undistributed_rewards = Uint128::zero();
+            state.undistributed_sent += amount;
+            response = response
+                .add_message(erth_transfer_msg(&addrs.erth_token, sink, amount)?)
+                .add_attribute("undistributed_sent", amount.to_string());
+        }
+    }
     STATE.save(deps.storage, &state)?;
-    Ok(Response::new().add_attribute("action", "manual_update_pool_rewards_complete"))
-}
\ No newline at end of file
+
+    Ok(response)
+}
+
+pub fn erth_transfer_msg(erth_token: &ContractInfo, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: erth_token.address.to_string(),
+        code_hash: erth_token.code_hash.clone(),
+        msg: to_binary(&snip20::HandleMsg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+            padding: None,
+            memo: None,
+        })?,
+        funds: vec![],
+    }))
+}
@@ -3,6 +3,7 @@ use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, Uint128, Addr,
 use secret_toolkit::snip20;
 
 use crate::state::{CONFIG, STATE, PoolInfo, POOL_INFO, load_contracts};
+use crate::execute::ensure_pool_active;
 
 
 #[derive(Debug, Clone)]
@@ -104,6 +105,7 @@ pub fn anml_buyback_swap(
     let mut anml_pool_info = POOL_INFO
         .get(deps.storage, &addrs.anml_token.address)
         .ok_or_else(|| StdError::generic_err("ANML pool not found"))?;
+    ensure_pool_active(&anml_pool_info)?;
 
     // Calculate swap details (no fees)
     let output_amount = calculate_amm_swap(&addrs.erth_token.address, config.protocol_fee, &anml_pool_info, &input_token, amount, false)?.output_amount;
@@ -111,7 +113,7 @@ pub fn anml_buyback_swap(
     // Update pool reserves
     anml_pool_info.state.erth_reserve += amount;
     anml_pool_info.state.token_b_reserve -= output_amount;
-    anml_pool_info.state.daily_volumes[0] += amount;
+    anml_pool_info.state.record_volume(amount);
     POOL_INFO.insert(deps.storage, &addrs.anml_token.address, &anml_pool_info)?;
 
     // Track total ANML burned
@@ -276,6 +278,7 @@ pub fn execute_swap_logic(
         let mut input_pool_info = POOL_INFO
             .get(deps.storage, input_token)
             .ok_or_else(|| StdError::generic_err("No pool found for input token"))?;
+        ensure_pool_active(&input_pool_info)?;
 
         let calc1 = calculate_amm_swap(erth_addr, fee_rate, &input_pool_info, input_token, amount, with_fees)?;
 
@@ -295,13 +298,14 @@ pub fn execute_swap_logic(
 
         let (intermediate_amount, vol_step1) = (calc1.output_amount, calc1.trade_volume);
 
-        input_pool_info.state.daily_volumes[0] += vol_step1;
+        input_pool_info.state.record_volume(vol_step1);
         POOL_INFO.insert(deps.storage, input_token, &input_pool_info)?;
 
         // Step 2: ERTH -> output_token
         let mut output_pool_info = POOL_INFO
             .get(deps.storage, output_token)
             .ok_or_else(|| StdError::generic_err("No pool found for output token"))?;
+        ensure_pool_active(&output_pool_info)?;
 
         let calc2 = calculate_amm_swap(erth_addr, fee_rate, &output_pool_info, erth_addr, intermediate_amount, with_fees)?;
 
@@ -315,7 +319,7 @@ pub fn execute_swap_logic(
 
         let (final_output_amount, vol_step2) = (calc2.output_amount, calc2.trade_volume);
 
-        output_pool_info.state.daily_volumes[0] += vol_step2;
+        output_pool_info.state.record_volume(vol_step2);
         POOL_INFO.insert(deps.storage, output_token, &output_pool_info)?;
 
         let total_fee = fee_step1 + fee_step2;
@@ -371,6 +375,7 @@ pub fn execute_swap_logic(
             let mut pool_info = POOL_INFO
                 .get(deps.storage, &pool_addr)
                 .ok_or_else(|| StdError::generic_err("No pool found for output token"))?;
+            ensure_pool_active(&pool_info)?;
 
             let calc = calculate_amm_swap(erth_addr, fee_rate, &pool_info, erth_addr, amount, with_fees)?;
 
@@ -384,7 +389,7 @@ pub fn execute_swap_logic(
 
             let (output_amount, trade_volume) = (calc.output_amount, calc.trade_volume);
 
-            pool_info.state.daily_volumes[0] += trade_volume;
+            pool_info.state.record_volume(trade_volume);
             POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
 
             // Create burn message if there are fees
@@ -434,6 +439,7 @@ pub fn execute_swap_logic(
             let mut pool_info = POOL_INFO
                 .get(deps.storage, &pool_addr)
                 .ok_or_else(|| StdError::generic_err("No pool found for input token"))?;
+            ensure_pool_active(&pool_info)?;
 
             let calc = calculate_amm_swap(erth_addr, fee_rate, &pool_info, input_token, amount, with_fees)?;
 
@@ -447,7 +453,7 @@ pub fn execute_swap_logic(
 
             let (output_amount, trade_volume) = (calc.output_amount, calc.trade_volume);
 
-            pool_info.state.daily_volumes[0] += trade_volume;
+            pool_info.state.record_volume(trade_volume);
             POOL_INFO.insert(deps.storage, &pool_addr, &pool_info)?;
 
             // Create burn message if there are fees
@@ -545,6 +551,7 @@ pub fn swap_to_erth_and_burn(
     let mut pool_info = POOL_INFO
         .get(deps.storage, &input_token)
         .ok_or_else(|| StdError::generic_err("Pool not found for input token"))?;
+    ensure_pool_active(&pool_info)?;
 
     // Ensure this pool is actually keyed by its token_b contract
     if input_token != pool_info.config.token_b_contract {
@@ -561,7 +568,7 @@ pub fn swap_to_erth_and_burn(
 
     // Daily volume is tracked in ERTH terms based on input value against current reserves
     let trade_volume = erth_out;
-    pool_info.state.daily_volumes[0] += trade_volume;
+    pool_info.state.record_volume(trade_volume);
 
     // Persist pool updates
     POOL_INFO.insert(deps.storage, &input_token, &pool_info)?;
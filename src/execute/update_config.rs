@@ -1,6 +1,6 @@
 // src/execute/config.rs
-use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult};
-use crate::state::{CONFIG, Config};
+use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdError, StdResult, Uint128};
+use crate::state::{CONFIG, Config, MAX_VOLUME_WINDOW};
 
 pub fn update_config(
     deps: DepsMut,
@@ -13,6 +13,42 @@ pub fn update_config(
     if info.sender != old_config.contract_manager {
         return Err(StdError::generic_err("Unauthorized"));
     }
+
+    if config.instant_unbond_penalty > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("instant_unbond_penalty cannot exceed 10000 basis points"));
+    }
+    if config.lock_tiers.iter().any(|tier| tier.multiplier < Uint128::from(10000u32)) {
+        return Err(StdError::generic_err("Lock multipliers cannot be below 10000 basis points"));
+    }
+    if config.reward_stream_seconds == 0 {
+        return Err(StdError::generic_err("reward_stream_seconds must be greater than zero"));
+    }
+    if config.gauge_epoch_seconds == 0 {
+        return Err(StdError::generic_err("gauge_epoch_seconds must be greater than zero"));
+    }
+    for (i, source) in config.allocation_sources.iter().enumerate() {
+        if config.allocation_sources[..i].iter().any(|s| s.allocation_id == source.allocation_id) {
+            return Err(StdError::generic_err("Duplicate allocation_id in allocation_sources"));
+        }
+    }
+    if config.volume_bucket_seconds == 0 {
+        return Err(StdError::generic_err("volume_bucket_seconds must be greater than zero"));
+    }
+    if config.volume_window == 0 || config.volume_window > MAX_VOLUME_WINDOW {
+        return Err(StdError::generic_err(format!("volume_window must be between 1 and {}", MAX_VOLUME_WINDOW)));
+    }
+    if config.volume_decay > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("volume_decay cannot exceed 10000 basis points"));
+    }
+    if config.early_vesting_penalty > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("early_vesting_penalty cannot exceed 10000 basis points"));
+    }
+    if config.keeper_bounty > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("keeper_bounty cannot exceed 10000 basis points"));
+    }
+    if config.gauge_vote_weight > Uint128::from(10000u32) {
+        return Err(StdError::generic_err("gauge_vote_weight cannot exceed 10000 basis points"));
+    }
     
     CONFIG.save(deps.storage, &config)?;
     
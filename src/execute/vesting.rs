@@ -0,0 +1,138 @@
+use cosmwasm_std::{to_binary, Addr, CosmosMsg, DepsMut, Env, MessageInfo, Response, StdError, StdResult,
+    Storage, Uint128, WasmMsg};
+use secret_toolkit::snip20;
+
+use crate::execute::rewards::erth_transfer_msg;
+use crate::state::{CONFIG, STATE, REWARD_VESTING, VestingEntry, load_contracts};
+
+// Past this many schedules new rewards join the newest one
+pub const MAX_VESTING_ENTRIES: usize = 16;
+
+
+// Takes everything vested so far and drops finished entries
+fn release_vested(entries: &mut Vec<VestingEntry>, now: u64) -> Uint128 {
+    let mut released = Uint128::zero();
+    for entry in entries.iter_mut() {
+        let vested = entry.vested(now);
+        released += vested - entry.claimed;
+        entry.claimed = vested;
+    }
+    entries.retain(|entry| entry.claimed < entry.amount);
+    released
+}
+
+// Puts `amount` on a new schedule ending `duration` from now, releasing
+// whatever the user's earlier schedules have vested. Returns the released amount.
+pub fn vest_rewards(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    amount: Uint128,
+    now: u64,
+    duration: u64,
+) -> StdResult<Uint128> {
+    let mut entries = REWARD_VESTING.get(storage, user).unwrap_or_default();
+    let released = release_vested(&mut entries, now);
+
+    if !amount.is_zero() {
+        if entries.len() >= MAX_VESTING_ENTRIES {
+            // Restart the newest schedule with its remainder plus the new amount
+            let newest = entries.last_mut().unwrap();
+            newest.amount = newest.amount - newest.claimed + amount;
+            newest.claimed = Uint128::zero();
+            newest.start_time = now;
+            newest.end_time = now + duration;
+        } else {
+            entries.push(VestingEntry {
+                amount,
+                claimed: Uint128::zero(),
+                start_time: now,
+                end_time: now + duration,
+            });
+        }
+    }
+
+    if entries.is_empty() {
+        REWARD_VESTING.remove(storage, user)?;
+    } else {
+        REWARD_VESTING.insert(storage, user, &entries)?;
+    }
+    Ok(released)
+}
+
+// Unvested amount across the entries and the penalty for taking it now
+pub fn early_claim(entries: &[VestingEntry], now: u64, penalty_rate: Uint128) -> (Uint128, Uint128) {
+    let unvested: Uint128 = entries.iter().map(|entry| entry.amount - entry.vested(now)).sum();
+    (unvested, unvested.multiply_ratio(penalty_rate, Uint128::from(10000u32)))
+}
+
+
+// -------------------------
+// Claim vested rewards
+// -------------------------
+pub fn claim_vested_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+
+    let released = vest_rewards(deps.storage, &info.sender, Uint128::zero(), env.block.time.seconds(), 0)?;
+    if released.is_zero() {
+        return Err(StdError::generic_err("No vested rewards to claim"));
+    }
+
+    Ok(Response::new()
+        .add_message(erth_transfer_msg(&addrs.erth_token, &info.sender, released)?)
+        .add_attribute("action", "claim_vested_rewards")
+        .add_attribute("user", info.sender)
+        .add_attribute("amount", released.to_string()))
+}
+
+// -------------------------
+// Claim all vesting rewards now, burning the early claim penalty
+// -------------------------
+pub fn claim_unvested_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> StdResult<Response> {
+    let config = CONFIG.load(deps.storage)?;
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    let mut state = STATE.load(deps.storage)?;
+    let now = env.block.time.seconds();
+
+    let mut entries = REWARD_VESTING
+        .get(deps.storage, &info.sender)
+        .ok_or_else(|| StdError::generic_err("No vesting rewards"))?;
+    let (unvested, penalty) = early_claim(&entries, now, config.early_vesting_penalty);
+    let vested = release_vested(&mut entries, now);
+    REWARD_VESTING.remove(deps.storage, &info.sender)?;
+
+    let payout = vested + unvested - penalty;
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !penalty.is_zero() {
+        state.erth_burned += penalty;
+        STATE.save(deps.storage, &state)?;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: addrs.erth_token.address.to_string(),
+            code_hash: addrs.erth_token.code_hash.clone(),
+            msg: to_binary(&snip20::HandleMsg::Burn {
+                amount: penalty,
+                memo: None,
+                padding: None,
+            })?,
+            funds: vec![],
+        }));
+    }
+    if !payout.is_zero() {
+        messages.push(erth_transfer_msg(&addrs.erth_token, &info.sender, payout)?);
+    }
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attribute("action", "claim_unvested_rewards")
+        .add_attribute("user", info.sender)
+        .add_attribute("amount", payout.to_string())
+        .add_attribute("penalty_burned", penalty.to_string()))
+}
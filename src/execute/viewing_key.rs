@@ -0,0 +1,36 @@
+use cosmwasm_std::{to_binary, DepsMut, Env, MessageInfo, Response, StdResult};
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
+
+use crate::msg::ViewingKeyResponse;
+
+
+// -------------------------
+// Create a random viewing key for the sender
+// -------------------------
+pub fn create_viewing_key(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    entropy: String,
+) -> StdResult<Response> {
+    let key = ViewingKey::create(deps.storage, &info, &env, info.sender.as_str(), entropy.as_bytes());
+
+    // The key is only returned as data, never as an attribute
+    Ok(Response::new()
+        .set_data(to_binary(&ViewingKeyResponse { key })?)
+        .add_attribute("action", "create_viewing_key"))
+}
+
+// -------------------------
+// Set a viewing key chosen by the sender
+// -------------------------
+pub fn set_viewing_key_for_sender(
+    deps: DepsMut,
+    info: MessageInfo,
+    key: String,
+) -> StdResult<Response> {
+    ViewingKey::set(deps.storage, info.sender.as_str(), &key);
+
+    Ok(Response::new()
+        .add_attribute("action", "set_viewing_key"))
+}
@@ -4,7 +4,11 @@ use cosmwasm_std::{DepsMut, Env, MessageInfo, Response, StdResult, Uint128, to_b
 use secret_toolkit::snip20;
 
 use crate::msg::InstantiateMsg;
-use crate::state::{Config, STATE, CONFIG, State, load_contracts};
+use crate::state::{Config, STATE, CONFIG, State, load_contracts, DEFAULT_INSTANT_UNBOND_PENALTY, default_lock_tiers, DEFAULT_GAUGE_EPOCH_SECONDS,
+    DEFAULT_REWARD_STREAM_SECONDS, default_allocation_sources,
+    DEFAULT_KEEPER_BOUNTY, DEFAULT_MIN_UPKEEP_INTERVAL,
+    DEFAULT_VOLUME_BUCKET_SECONDS, DEFAULT_VOLUME_WINDOW, DEFAULT_EARLY_VESTING_PENALTY,
+    STORAGE_VERSION, CURRENT_STORAGE_VERSION};
 
 pub fn perform_instantiate(
     deps: DepsMut,
@@ -15,26 +19,47 @@ pub fn perform_instantiate(
     let contract_manager = deps.api.addr_validate(&msg.contract_manager)?;
     let registry_contract = deps.api.addr_validate(&msg.registry_contract)?;
 
-    let config = Config {
+    let mut config = Config {
         contract_manager,
         registry_contract,
         registry_hash: msg.registry_hash.clone(),
         unbonding_seconds: msg.unbonding_seconds,
         unbonding_window: msg.unbonding_window,
         protocol_fee: Uint128::from(50u32),
+        instant_unbond_penalty: Uint128::from(DEFAULT_INSTANT_UNBOND_PENALTY),
+        burn_unbond_penalties: true,
+        lock_tiers: default_lock_tiers(),
+        gauge_epoch_seconds: DEFAULT_GAUGE_EPOCH_SECONDS,
+        gauge_vote_weight: Uint128::zero(),
+        reward_stream_seconds: DEFAULT_REWARD_STREAM_SECONDS,
+        allocation_sources: vec![],
+        keeper_bounty: Uint128::from(DEFAULT_KEEPER_BOUNTY),
+        min_upkeep_interval: DEFAULT_MIN_UPKEEP_INTERVAL,
+        volume_bucket_seconds: DEFAULT_VOLUME_BUCKET_SECONDS,
+        volume_window: DEFAULT_VOLUME_WINDOW,
+        volume_decay: Uint128::from(10000u32),
+        undistributed_sink: None,
+        reward_vesting_seconds: 0,
+        early_vesting_penalty: Uint128::from(DEFAULT_EARLY_VESTING_PENALTY),
     };
 
+    // Query registry for contract addresses
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    config.allocation_sources = default_allocation_sources(&addrs);
+
     let state = State {
         erth_burned: Uint128::zero(),
         anml_burned: Uint128::zero(),
         pending_reward: Uint128::zero(),
+        allocations_received: vec![],
+        last_upkeep: 0,
+        undistributed_rewards: Uint128::zero(),
+        undistributed_sent: Uint128::zero(),
     };
 
     CONFIG.save(deps.storage, &config)?;
     STATE.save(deps.storage, &state)?;
-
-    // Query registry for contract addresses
-    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    STORAGE_VERSION.save(deps.storage, &CURRENT_STORAGE_VERSION)?;
 
     // Register this contract as a receiver for ERTH
     let register_erth_msg = CosmosMsg::Wasm(WasmMsg::Execute {
@@ -1,7 +1,8 @@
 // src/lib.rs
 use cosmwasm_std::{entry_point, Binary, Deps, DepsMut, Env, MessageInfo, Response, 
     StdResult, StdError, Reply,};
-use crate::execute::{execute_dispatch, handle_pool_rewards_update_reply};
+use crate::execute::{execute_dispatch, handle_pool_rewards_update_reply,
+    handle_lp_token_instantiate_reply};
 
 use crate::query::query_dispatch;
 use crate::migrate::perform_migration;
@@ -15,6 +16,7 @@ pub mod migrate;
 pub mod instantiate;
 
 const POOL_REWARDS_UPDATE_REPLY_ID: u64 = 1;
+const LP_TOKEN_INSTANTIATE_REPLY_ID: u64 = 2;
 
 #[entry_point]
 pub fn instantiate(
@@ -58,6 +60,7 @@ pub fn query(
 pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> StdResult<Response> {
     match msg.id {
         POOL_REWARDS_UPDATE_REPLY_ID => handle_pool_rewards_update_reply(deps, env),
+        LP_TOKEN_INSTANTIATE_REPLY_ID => handle_lp_token_instantiate_reply(deps, env, msg),
         _ => Err(StdError::generic_err("Unknown reply ID")),
     }
 }
@@ -1,14 +1,22 @@
 // src/migrate.rs
 use cosmwasm_std::{DepsMut, Env, Response, StdResult, to_binary, CosmosMsg, WasmMsg,
-    StdError, Addr, Uint128,
+    StdError, Addr, Uint128, Uint256,
 };
 
 use crate::msg::MigrateMsg;
-use crate::state::{Config, CONFIG, POOL_INFO, load_contracts};
+use crate::execute::SCALING_FACTOR;
+use crate::state::{Config, CONFIG, DEFAULT_INSTANT_UNBOND_PENALTY, POOL_INFO, PoolInfo, PoolState, PoolConfig, load_contracts,
+    UNBONDING_REQUESTS, UnbondRecord, LateClaimPolicy, USER_INFO, UserInfo, track_user_pool,
+    default_lock_tiers, DEFAULT_GAUGE_EPOCH_SECONDS, DEFAULT_REWARD_STREAM_SECONDS, default_allocation_sources,
+    DEFAULT_KEEPER_BOUNTY, DEFAULT_MIN_UPKEEP_INTERVAL,
+    DEFAULT_VOLUME_BUCKET_SECONDS, DEFAULT_VOLUME_WINDOW, DEFAULT_EARLY_VESTING_PENALTY,
+    STATE, State,
+    STORAGE_VERSION, CURRENT_STORAGE_VERSION,
+};
 
 use schemars::JsonSchema;
 use secret_toolkit::snip20;
-use secret_toolkit_storage::Item;
+use secret_toolkit_storage::{Item, Keymap};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -28,6 +36,81 @@ pub struct OldConfig {
 // Use the same storage key as CONFIG to read old format
 pub static OLD_CONFIG: Item<OldConfig> = Item::new(b"config");
 
+// Registry config layout from before instant unbonding
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldRegistryConfig {
+    pub contract_manager: Addr,
+    pub registry_contract: Addr,
+    pub registry_hash: String,
+    pub unbonding_seconds: u64,
+    pub unbonding_window: u64,
+    pub protocol_fee: Uint128,
+}
+
+pub static OLD_REGISTRY_CONFIG: Item<OldRegistryConfig> = Item::new(b"config");
+
+// State layout from before per-source allocation accounting
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldState {
+    pub erth_burned: Uint128,
+    pub anml_burned: Uint128,
+    pub pending_reward: Uint128,
+}
+
+// Same storage key as STATE
+pub static OLD_STATE: Item<OldState> = Item::new(b"state");
+
+// Pool layout from before LP tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldPoolState {
+    pub total_shares: Uint128,
+    pub reward_per_token_scaled: Uint128,
+    pub erth_reserve: Uint128,
+    pub token_b_reserve: Uint128,
+    pub daily_rewards: [Uint128; 7],
+    pub daily_volumes: [Uint128; 7],
+    pub last_updated_day: u64,
+    pub unbonding_shares: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldPoolConfig {
+    pub token_b_contract: Addr,
+    pub token_b_hash: String,
+    pub token_b_symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldPoolInfo {
+    pub state: OldPoolState,
+    pub config: OldPoolConfig,
+}
+
+// Same storage key as POOL_INFO
+pub static OLD_POOL_INFO: Keymap<Addr, OldPoolInfo> = Keymap::new(b"pool_info");
+
+// User layout from before time locks
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OldUserInfo {
+    pub amount_staked: Uint128,
+    pub reward_debt: Uint128,
+    pub pending_rewards: Uint128,
+}
+
+// Same storage key as USER_INFO
+pub static OLD_USER_INFO: Keymap<Addr, OldUserInfo> = Keymap::new(b"user_info");
+
+// Unbond record layout from before per-request unbonding settings
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OldUnbondRecord {
+    pub pool: Addr,
+    pub amount: Uint128,
+    pub start_time: u64,
+}
+
+// Same storage key as UNBONDING_REQUESTS
+pub static OLD_UNBONDING_REQUESTS: Keymap<Addr, Vec<OldUnbondRecord>> = Keymap::new(b"unbonding_requests");
+
 fn register_all_tokens(deps: &DepsMut, env: &Env, config: &Config) -> Result<Vec<CosmosMsg>, StdError> {
     let addrs = load_contracts(&deps.as_ref(), config)?;
     let mut messages: Vec<CosmosMsg> = vec![];
@@ -85,10 +168,155 @@ pub fn perform_migration(
     match msg {
         MigrateMsg::Migrate { registry_contract, registry_hash } =>
             migrate_to_registry(deps, env, registry_contract, registry_hash),
-        MigrateMsg::Upgrade {} => {
-            Ok(Response::new().add_attribute("action", "upgrade"))
+        MigrateMsg::Upgrade {} => upgrade_storage(deps),
+    }
+}
+
+// The old accumulator was scaled by 1e6 and old debts were in token units; both
+// move to SCALING_FACTOR so unsettled rewards carry over unchanged
+const OLD_SCALING_FACTOR: Uint256 = Uint256::from_u128(1_000_000);
+
+fn rescale_accumulator(old: Uint128) -> Uint256 {
+    Uint256::from(old) * (SCALING_FACTOR / OLD_SCALING_FACTOR)
+}
+
+// Rewrites config and pools stored in the old layout; a no-op once storage is current
+fn upgrade_storage(deps: DepsMut) -> Result<Response, StdError> {
+    match STORAGE_VERSION.may_load(deps.storage)? {
+        Some(CURRENT_STORAGE_VERSION) => return Ok(Response::new().add_attribute("action", "upgrade")),
+        // Only the original, unversioned layout has a conversion
+        Some(version) => {
+            return Err(StdError::generic_err(format!("Cannot upgrade from storage version {}", version)));
         }
+        None => {}
     }
+
+    let old_config = OLD_REGISTRY_CONFIG.load(deps.storage)?;
+    let mut config = Config {
+        contract_manager: old_config.contract_manager,
+        registry_contract: old_config.registry_contract,
+        registry_hash: old_config.registry_hash,
+        unbonding_seconds: old_config.unbonding_seconds,
+        unbonding_window: old_config.unbonding_window,
+        protocol_fee: old_config.protocol_fee,
+        instant_unbond_penalty: Uint128::from(DEFAULT_INSTANT_UNBOND_PENALTY),
+        burn_unbond_penalties: true,
+        lock_tiers: default_lock_tiers(),
+        gauge_epoch_seconds: DEFAULT_GAUGE_EPOCH_SECONDS,
+        gauge_vote_weight: Uint128::zero(),
+        reward_stream_seconds: DEFAULT_REWARD_STREAM_SECONDS,
+        allocation_sources: vec![],
+        keeper_bounty: Uint128::from(DEFAULT_KEEPER_BOUNTY),
+        min_upkeep_interval: DEFAULT_MIN_UPKEEP_INTERVAL,
+        volume_bucket_seconds: DEFAULT_VOLUME_BUCKET_SECONDS,
+        volume_window: DEFAULT_VOLUME_WINDOW,
+        volume_decay: Uint128::from(10000u32),
+        undistributed_sink: None,
+        reward_vesting_seconds: 0,
+        early_vesting_penalty: Uint128::from(DEFAULT_EARLY_VESTING_PENALTY),
+    };
+    let addrs = load_contracts(&deps.as_ref(), &config)?;
+    config.allocation_sources = default_allocation_sources(&addrs);
+    CONFIG.save(deps.storage, &config)?;
+
+    let old_state = OLD_STATE.load(deps.storage)?;
+    STATE.save(deps.storage, &State {
+        erth_burned: old_state.erth_burned,
+        anml_burned: old_state.anml_burned,
+        pending_reward: old_state.pending_reward,
+        allocations_received: vec![],
+        last_upkeep: 0,
+        undistributed_rewards: Uint128::zero(),
+        undistributed_sent: Uint128::zero(),
+    })?;
+
+    let old_pools: Vec<_> = OLD_POOL_INFO
+        .iter(deps.storage)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (pool_addr, old) in old_pools.iter() {
+        let mut pool_info = PoolInfo {
+            state: PoolState {
+                total_shares: old.state.total_shares,
+                reward_per_token_scaled: rescale_accumulator(old.state.reward_per_token_scaled),
+                erth_reserve: old.state.erth_reserve,
+                token_b_reserve: old.state.token_b_reserve,
+                reward_history: old.state.daily_rewards.to_vec(),
+                volume_history: old.state.daily_volumes.to_vec(),
+                history_start: old.state.last_updated_day * 86400,
+                unbonding_shares: old.state.unbonding_shares,
+                staked_shares: Uint128::zero(),
+                lp_token_supply: Uint128::zero(),
+                boosted_shares: Uint128::zero(),
+                boost_expiries: vec![],
+                deprecated: false,
+                incentives: vec![],
+                reward_rate: Uint256::zero(),
+                period_finish: 0,
+                last_reward_update: 0,
+                undistributed_rewards: Uint128::zero(),
+            },
+            config: PoolConfig {
+                token_b_contract: old.config.token_b_contract.clone(),
+                token_b_hash: old.config.token_b_hash.clone(),
+                token_b_symbol: old.config.token_b_symbol.clone(),
+                lp_token: None,
+                unbonding_seconds: None,
+                unbonding_window: None,
+                late_claim_policy: LateClaimPolicy::AutoRestake,
+            },
+        };
+
+        // Pending requests were made under the global settings
+        let old_unbonding_by_pool = OLD_UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
+        let unbonding_by_pool = UNBONDING_REQUESTS.add_suffix(pool_addr.as_bytes());
+        let old_requests: Vec<_> = old_unbonding_by_pool
+            .iter(deps.storage)?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (user, old_records) in old_requests {
+            let records: Vec<UnbondRecord> = old_records
+                .into_iter()
+                .map(|r| UnbondRecord {
+                    pool: r.pool,
+                    amount: r.amount,
+                    start_time: r.start_time,
+                    unbonding_seconds: old_config.unbonding_seconds,
+                    unbonding_window: old_config.unbonding_window,
+                    late_claim_policy: LateClaimPolicy::AutoRestake,
+                })
+                .collect();
+            unbonding_by_pool.insert(deps.storage, &user, &records)?;
+            track_user_pool(deps.storage, &user, pool_addr)?;
+        }
+
+        // Existing stakes have no locks; also backfill the per-user pool index
+        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
+        let old_stakers: Vec<_> = OLD_USER_INFO
+            .add_suffix(pool_addr.as_bytes())
+            .iter(deps.storage)?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (user, old_user_info) in old_stakers {
+            user_info_by_pool.insert(deps.storage, &user, &UserInfo {
+                amount_staked: old_user_info.amount_staked,
+                reward_debt: Uint256::from(old_user_info.reward_debt) * SCALING_FACTOR,
+                pending_rewards: old_user_info.pending_rewards,
+                locks: vec![],
+                incentives: vec![],
+            })?;
+            track_user_pool(deps.storage, &user, pool_addr)?;
+            pool_info.state.staked_shares += old_user_info.amount_staked;
+        }
+
+        // Rewards used to be divided over total_shares, including unbonding
+        // shares; from here on only the stakes counted above earn
+        POOL_INFO.insert(deps.storage, pool_addr, &pool_info)?;
+    }
+
+    STORAGE_VERSION.save(deps.storage, &CURRENT_STORAGE_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "upgrade")
+        .add_attribute("pools_migrated", old_pools.len().to_string()))
 }
 
 fn migrate_to_registry(
@@ -102,14 +330,31 @@ fn migrate_to_registry(
 
     let registry_addr = deps.api.addr_validate(&registry_contract)?;
 
-    let new_config = Config {
+    let mut new_config = Config {
         contract_manager: old_config.contract_manager,
         registry_contract: registry_addr,
         registry_hash: registry_hash.clone(),
         unbonding_seconds: old_config.unbonding_seconds,
         unbonding_window: old_config.unbonding_window,
         protocol_fee: old_config.protocol_fee,
+        instant_unbond_penalty: Uint128::from(DEFAULT_INSTANT_UNBOND_PENALTY),
+        burn_unbond_penalties: true,
+        lock_tiers: default_lock_tiers(),
+        gauge_epoch_seconds: DEFAULT_GAUGE_EPOCH_SECONDS,
+        gauge_vote_weight: Uint128::zero(),
+        reward_stream_seconds: DEFAULT_REWARD_STREAM_SECONDS,
+        allocation_sources: vec![],
+        keeper_bounty: Uint128::from(DEFAULT_KEEPER_BOUNTY),
+        min_upkeep_interval: DEFAULT_MIN_UPKEEP_INTERVAL,
+        volume_bucket_seconds: DEFAULT_VOLUME_BUCKET_SECONDS,
+        volume_window: DEFAULT_VOLUME_WINDOW,
+        volume_decay: Uint128::from(10000u32),
+        undistributed_sink: None,
+        reward_vesting_seconds: 0,
+        early_vesting_penalty: Uint128::from(DEFAULT_EARLY_VESTING_PENALTY),
     };
+    let addrs = load_contracts(&deps.as_ref(), &new_config)?;
+    new_config.allocation_sources = default_allocation_sources(&addrs);
 
     CONFIG.save(deps.storage, &new_config)?;
 
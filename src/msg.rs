@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{Binary, Uint128, Addr,};
 
-use crate::state::{UserInfo, PoolInfo, PoolConfig, Config,};
+use crate::state::{UserInfo, PoolInfo, PoolConfig, Config, VestingEntry,};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct InstantiateMsg {
@@ -21,17 +21,71 @@ pub enum ExecuteMsg {
         amount_erth: Uint128,
         amount_b: Uint128,
         pool: String,
+        stake: Option<bool>,
     },
     RemoveLiquidity {
         pool: String,
         amount: Uint128,
     },
+    InstantRemoveLiquidity {
+        pool: String,
+        amount: Uint128,
+    },
     ClaimUnbondLiquidity {
         pool: String,
     },
+    ClaimAllUnbonded {},
+    ClaimUnbondFor {
+        pool: String,
+        user: String,
+    },
+    SetAutoClaimUnbond {
+        enabled: bool,
+    },
+    CancelUnbond {
+        pool: String,
+        // positions in the user's unbonding list; None cancels all of them
+        indices: Option<Vec<u32>>,
+    },
+    TransferPosition {
+        pool: String,
+        recipient: String,
+        shares: Uint128,
+    },
+    LockStake {
+        pool: String,
+        shares: Uint128,
+        duration: u64,
+    },
+    ReleaseExpiredLocks {
+        pool: String,
+        user: String,
+    },
+    // pools defaults to every pool the sender has a position in,
+    // recipient to the sender
     ClaimRewards {
+        pools: Option<Vec<String>>,
+        recipient: Option<String>,
+    },
+    ClaimVestedRewards {},
+    // everything still vesting, less the early claim penalty which is burned
+    ClaimUnvestedRewards {},
+    // claims incentive tokens alone; tokens defaults to all of them
+    ClaimIncentives {
+        pool: String,
+        tokens: Option<Vec<String>>,
+    },
+    CompoundRewards {
         pools: Vec<String>,
     },
+    CompoundRewardsFor {
+        user: String,
+        pools: Vec<String>,
+    },
+    // lets `keeper` call CompoundRewardsFor on the sender; None turns it off
+    SetAutoCompound {
+        keeper: Option<String>,
+    },
     UpdateConfig {
         config: Config,
     },
@@ -39,11 +93,52 @@ pub enum ExecuteMsg {
         token: String,
         hash: String,
         symbol: String,
+        lp_token_code: Option<LpTokenCode>,
+    },
+    CreateLpToken {
+        pool: String,
+        lp_token_code: LpTokenCode,
     },
     UpdatePoolConfig { 
         pool: String, 
         pool_config: PoolConfig,
     },
+    MigratePoolToken {
+        pool: String,
+        new_token: String,
+        new_hash: String,
+        // executed on the old token to convert the pool's reserves
+        token_migration_msg: Option<Binary>,
+    },
+    // Moves the next page of a migrating pool's records, finishing it once all have moved
+    ContinuePoolMigration {
+        limit: Option<u32>,
+    },
+    DeprecatePool {
+        pool: String,
+    },
+    RemovePool {
+        pool: String,
+    },
+    // manager only; once registered, anyone can deposit the token as an incentive
+    RegisterIncentiveToken {
+        token: String,
+        hash: String,
+    },
+    VoteGauges {
+        votes: Vec<PoolVote>,
+    },
+    // Re-checks a page of an epoch's voters; defaults to the current epoch
+    FinalizeGaugeEpoch {
+        epoch: Option<u64>,
+        limit: Option<u32>,
+    },
+    CreateViewingKey {
+        entropy: String,
+    },
+    SetViewingKey {
+        key: String,
+    },
     UpdatePoolRewards {},
     Receive {
         sender: String,
@@ -68,6 +163,32 @@ pub enum ReceiveMsg {
     AnmlBuybackSwap {},
     SwapToErthAndBurn {},
     SwapForGas {},
+    StakeLp {
+        pool: String,
+    },
+    RedeemLp {
+        pool: String,
+    },
+    // pays the sent token out to the pool's stakers between start_time
+    // (default now) and end_time
+    AddIncentive {
+        pool: String,
+        start_time: Option<u64>,
+        end_time: u64,
+    },
+}
+
+// weight is the share of the voter's power, in basis points
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PoolVote {
+    pub pool: String,
+    pub weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LpTokenCode {
+    pub code_id: u64,
+    pub code_hash: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -85,6 +206,7 @@ pub enum MigrateMsg {
         registry_contract: String,
         registry_hash: String,
     },
+    // converts older storage layouts, rescaling reward accumulators and debts to 1e18
     Upgrade {},
 }
 
@@ -96,13 +218,31 @@ pub enum QueryMsg {
     QueryPoolInfo {
         pools: Vec<String>, 
     },
+    // per-user queries need the user's viewing key
     QueryUserInfo { 
         pools: Vec<String>, 
         user: String,
+        viewing_key: String,
     },
     QueryUnbondingRequests { 
         pool: String, 
         user: String,
+        viewing_key: String,
+    },
+    // vote totals for an epoch, the current one by default
+    QueryGaugeWeights {
+        epoch: Option<u64>,
+    },
+    QueryGaugeVote {
+        user: String,
+        viewing_key: String,
+    },
+    QueryPoolApr {
+        pools: Vec<String>,
+    },
+    QueryVesting {
+        user: String,
+        viewing_key: String,
     },
     SimulateSwap {
         input_token: String,
@@ -112,6 +252,12 @@ pub enum QueryMsg {
 }
 
 
+// Set as the response data of CreateViewingKey
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ViewingKeyResponse {
+    pub key: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct UserInfoResponse {
     pub pool_info: PoolInfo,
@@ -126,6 +272,39 @@ pub struct SimulateSwapResponse {
     pub price_impact: Uint128,          // Price impact in basis points (e.g., 250 = 2.5%)
 }
 
+// Values in ERTH, APRs in basis points. Rewards and volume are totals over the
+// configured volume window of window_seconds. reward_apr is for an unlocked
+// stake; lock multipliers scale it up.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PoolAprResponse {
+    pub pool: Addr,
+    pub tvl: Uint128,
+    pub window_seconds: u64,
+    pub window_rewards: Uint128,
+    pub window_volume: Uint128,
+    pub reward_apr: Uint128,
+    pub fee_apr: Uint128,
+}
+
+// vested is claimable now; claiming the unvested amount early costs early_claim_penalty
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VestingResponse {
+    pub entries: Vec<VestingEntry>,
+    pub vested: Uint128,
+    pub unvested: Uint128,
+    pub early_claim_penalty: Uint128,
+}
+
+// Set as the response data of ClaimAllUnbonded
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UnbondClaimResult {
+    pub pool: Addr,
+    pub shares_claimed: Uint128,
+    pub shares_restaked: Uint128,
+    pub erth_returned: Uint128,
+    pub token_b_returned: Uint128,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Snip20InstantiateMsg {
     pub name: String,
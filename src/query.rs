@@ -1,21 +1,39 @@
 // src/query/mod.rs
 use cosmwasm_std::{Deps, Env, Binary, StdResult, to_binary, Uint128, StdError };
-use crate::msg::{QueryMsg, UserInfoResponse, SimulateSwapResponse};
-use crate::state::{STATE, State, Config, CONFIG, UserInfo, USER_INFO, POOL_INFO, PoolInfo,
+use crate::msg::{QueryMsg, UserInfoResponse, SimulateSwapResponse, PoolAprResponse, VestingResponse};
+use crate::state::{STATE, State, Config, CONFIG, USER_INFO, POOL_INFO, PoolInfo,
     UNBONDING_REQUESTS, UnbondRecord, load_contracts,
+    GAUGE_VOTES, GAUGE_WEIGHTS, GaugeVoteRecord, GaugeWeight, REWARD_VESTING,
     };
-use crate::execute::{update_user_rewards, calculate_amm_swap};
+use crate::execute::vesting::early_claim;
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
+use crate::execute::lock::settle_rewards;
+use crate::execute::{reward_shares, accrue_rewards, calculate_amm_swap, ensure_pool_active};
 
 
-pub fn query_dispatch(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query_dispatch(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::QueryState {} => to_binary(&query_state(deps)?),
         QueryMsg::QueryConfig {} => to_binary(&query_config(deps)?),
         QueryMsg::QueryPoolInfo {pools} => to_binary(&query_pool_info(deps, pools)?),
-        QueryMsg::QueryUserInfo {pools, user} => to_binary(&query_user_info(deps, pools, user)?),
-        QueryMsg::QueryUnbondingRequests { pool, user } => {
+        QueryMsg::QueryUserInfo {pools, user, viewing_key} => {
+            authenticate(deps, &user, &viewing_key)?;
+            to_binary(&query_user_info(deps, env, pools, user)?)
+        },
+        QueryMsg::QueryUnbondingRequests { pool, user, viewing_key } => {
+            authenticate(deps, &user, &viewing_key)?;
             to_binary(&query_unbonding_requests(deps, pool, user)?)
         },
+        QueryMsg::QueryGaugeWeights { epoch } => to_binary(&query_gauge_weights(deps, env, epoch)?),
+        QueryMsg::QueryGaugeVote { user, viewing_key } => {
+            authenticate(deps, &user, &viewing_key)?;
+            to_binary(&query_gauge_vote(deps, user)?)
+        },
+        QueryMsg::QueryPoolApr { pools } => to_binary(&query_pool_apr(deps, pools)?),
+        QueryMsg::QueryVesting { user, viewing_key } => {
+            authenticate(deps, &user, &viewing_key)?;
+            to_binary(&query_vesting(deps, env, user)?)
+        },
         QueryMsg::SimulateSwap {
             input_token,
             amount,
@@ -24,6 +42,14 @@ pub fn query_dispatch(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary>
     }
 }
 
+// Per-user queries only answer to the holder of the user's viewing key
+fn authenticate(deps: Deps, user: &str, viewing_key: &str) -> StdResult<()> {
+    let user_addr = deps.api.addr_validate(user)?;
+    // Fails the same way whether the key is wrong or was never set
+    ViewingKey::check(deps.storage, user_addr.as_str(), viewing_key)
+        .map_err(|_| StdError::generic_err("Wrong viewing key for this address or viewing key not set"))
+}
+
 fn query_state(deps: Deps) -> StdResult<State> {
     let state = STATE.load(deps.storage)?;
     Ok(state)
@@ -61,6 +87,7 @@ fn query_pool_info(
 
 fn query_user_info(
     deps: Deps,
+    env: Env,
     pools: Vec<String>,
     user: String,
 ) -> StdResult<Vec<UserInfoResponse>> {
@@ -71,7 +98,7 @@ fn query_user_info(
     for pool_str in pools {
         let pool_addr = deps.api.addr_validate(&pool_str)?;
 
-        let pool_info = match POOL_INFO.get(deps.storage, &pool_addr) {
+        let mut pool_info = match POOL_INFO.get(deps.storage, &pool_addr) {
             Some(info) => info,
             None => {
                 // If pool not found, skip or return an empty result for this pool.
@@ -79,25 +106,22 @@ fn query_user_info(
                 continue;
             }
         };
+        accrue_rewards(&mut pool_info, env.block.time.seconds())?;
 
-        let user_info_by_pool = USER_INFO.add_suffix(&pool_addr.as_bytes());
+        let user_info_by_pool = USER_INFO.add_suffix(pool_addr.as_bytes());
         // Default to zeroed user info if not found
         let mut user_info = user_info_by_pool
             .get(deps.storage, &user_addr)
-            .unwrap_or(UserInfo {
-                amount_staked: Uint128::zero(),
-                reward_debt: Uint128::zero(),
-                pending_rewards: Uint128::zero(),
-            });
+            .unwrap_or_default();
 
         // Only update rewards if user has any stake or pending rewards to begin with
         if user_info.amount_staked > Uint128::zero() || user_info.pending_rewards > Uint128::zero() {
-            update_user_rewards(&pool_info, &mut user_info)?;
+            settle_rewards(&mut pool_info, &mut user_info, env.block.time.seconds())?;
         }
 
         results.push(UserInfoResponse {
-            pool_info: pool_info,
-            user_info: user_info,
+            pool_info,
+            user_info,
         });
     }
 
@@ -122,6 +146,94 @@ fn query_unbonding_requests(
 }
 
 
+fn query_gauge_weights(
+    deps: Deps,
+    env: Env,
+    epoch: Option<u64>,
+) -> StdResult<Vec<GaugeWeight>> {
+    let config = CONFIG.load(deps.storage)?;
+    let epoch = epoch.unwrap_or(env.block.time.seconds() / config.gauge_epoch_seconds);
+    Ok(GAUGE_WEIGHTS.get(deps.storage, &epoch).unwrap_or_default())
+}
+
+fn query_gauge_vote(
+    deps: Deps,
+    user: String,
+) -> StdResult<Option<GaugeVoteRecord>> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    Ok(GAUGE_VOTES.get(deps.storage, &user_addr))
+}
+
+
+fn query_pool_apr(
+    deps: Deps,
+    pools: Vec<String>,
+) -> StdResult<Vec<PoolAprResponse>> {
+    let config = CONFIG.load(deps.storage)?;
+    let window_seconds = config.volume_bucket_seconds * config.volume_window as u64;
+    let mut results = vec![];
+
+    for pool_str in pools {
+        let pool_addr = deps.api.addr_validate(&pool_str)?;
+        let pool_info = match POOL_INFO.get(deps.storage, &pool_addr) {
+            Some(info) => info,
+            None => continue,
+        };
+        let state = &pool_info.state;
+
+        // Token B is valued at the pool's own price, so it matches the ERTH side
+        let tvl = state.erth_reserve * Uint128::from(2u32);
+        let window_rewards: Uint128 = state.reward_history.iter().cloned().sum();
+        let window_volume: Uint128 = state.volume_history.iter().cloned().sum();
+
+        // Yearly rewards per reward share over the value of a share
+        let reward_apr = if tvl.is_zero() || reward_shares(state).is_zero() {
+            Uint128::zero()
+        } else {
+            let yearly_rewards = window_rewards.multiply_ratio(365u64 * 86400, window_seconds);
+            yearly_rewards
+                .multiply_ratio(state.total_shares, reward_shares(state))
+                .multiply_ratio(10000u32, tvl)
+        };
+
+        results.push(PoolAprResponse {
+            pool: pool_addr,
+            tvl,
+            window_seconds,
+            window_rewards,
+            window_volume,
+            reward_apr,
+            // Swap fees are burned rather than paid to LPs, so there is no fee yield yet
+            fee_apr: Uint128::zero(),
+        });
+    }
+
+    Ok(results)
+}
+
+
+fn query_vesting(
+    deps: Deps,
+    env: Env,
+    user: String,
+) -> StdResult<VestingResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let user_addr = deps.api.addr_validate(&user)?;
+    let now = env.block.time.seconds();
+
+    let entries = REWARD_VESTING.get(deps.storage, &user_addr).unwrap_or_default();
+    let vested = entries.iter().map(|entry| entry.vested(now) - entry.claimed).sum();
+    let (unvested, early_claim_penalty) = early_claim(&entries, now, config.early_vesting_penalty);
+
+    Ok(VestingResponse {
+        entries,
+        vested,
+        unvested,
+        early_claim_penalty,
+    })
+}
+
+
 fn simulate_swap(
     deps: Deps,
     input_token_str: String,
@@ -146,6 +258,7 @@ fn simulate_swap(
         let input_pool_info = POOL_INFO
             .get(deps.storage, &input_token)
             .ok_or_else(|| StdError::generic_err("No pool found for input token"))?;
+        ensure_pool_active(&input_pool_info)?;
         let calc1 = calculate_amm_swap(erth_addr, fee_rate, &input_pool_info, &input_token, amount, true)?;
         intermediate_amount = calc1.output_amount;
         total_fee += calc1.protocol_fee;
@@ -153,6 +266,8 @@ fn simulate_swap(
         let output_pool_info = POOL_INFO
             .get(deps.storage, &output_token)
             .ok_or_else(|| StdError::generic_err("No pool found for output token"))?;
+
+        ensure_pool_active(&output_pool_info)?;
         let calc2 = calculate_amm_swap(erth_addr, fee_rate, &output_pool_info, erth_addr, intermediate_amount, true)?;
         final_output_amount = calc2.output_amount;
         total_fee += calc2.protocol_fee;
@@ -166,6 +281,7 @@ fn simulate_swap(
             let pool_info = POOL_INFO
                 .get(deps.storage, &output_token)
                 .ok_or_else(|| StdError::generic_err("No pool found for token"))?;
+            ensure_pool_active(&pool_info)?;
             let calc = calculate_amm_swap(erth_addr, fee_rate, &pool_info, erth_addr, amount, true)?;
             final_output_amount = calc.output_amount;
             total_fee += calc.protocol_fee;
@@ -175,6 +291,7 @@ fn simulate_swap(
             let pool_info = POOL_INFO
                 .get(deps.storage, &input_token)
                 .ok_or_else(|| StdError::generic_err("No pool found for token"))?;
+            ensure_pool_active(&pool_info)?;
             let calc = calculate_amm_swap(erth_addr, fee_rate, &pool_info, &input_token, amount, true)?;
             final_output_amount = calc.output_amount;
             total_fee += calc.protocol_fee;
@@ -12,10 +12,84 @@ pub struct Config {
     pub unbonding_seconds: u64,
     pub unbonding_window: u64,
     pub protocol_fee: Uint128,
+    // basis points taken from reserves on InstantRemoveLiquidity
+    pub instant_unbond_penalty: Uint128,
+    // burn instant and late unbond penalties, otherwise they stay in the pool for remaining LPs
+    pub burn_unbond_penalties: bool,
+    pub lock_tiers: Vec<LockTier>,
+    // votes cast in one epoch steer emissions in the next
+    pub gauge_epoch_seconds: u64,
+    // basis points of emissions split by gauge votes, the rest by volume
+    pub gauge_vote_weight: Uint128,
+    // each distribution is paid out linearly over this many seconds
+    pub reward_stream_seconds: u64,
+    // allocations claimed into pending_reward
+    pub allocation_sources: Vec<AllocationSource>,
+    // basis points of each distribution paid to whoever triggered it
+    pub keeper_bounty: Uint128,
+    // seconds that must pass between UpdatePoolRewards calls
+    pub min_upkeep_interval: u64,
+    // pool volume is kept in volume_window buckets of volume_bucket_seconds each
+    pub volume_bucket_seconds: u64,
+    pub volume_window: u32,
+    // basis points each older bucket counts for relative to the one after it;
+    // 10000 weights the whole window equally
+    pub volume_decay: Uint128,
+    // receives rewards that could not be distributed; when unset they roll
+    // into the next distribution
+    pub undistributed_sink: Option<Addr>,
+    // claimed and withdrawn ERTH rewards vest linearly over this many seconds;
+    // 0 pays them out directly and compounding is only allowed then
+    pub reward_vesting_seconds: u64,
+    // basis points of the unvested amount burned when claiming it early
+    pub early_vesting_penalty: Uint128,
+}
+
+// An allocation claimable from a contract with SendMsg::ClaimAllocation
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AllocationSource {
+    pub allocation_id: u32,
+    pub contract: ContractInfo,
+}
+
+// Lock duration and the reward multiplier it earns, in basis points (10000 = 1x)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct LockTier {
+    pub duration: u64,
+    pub multiplier: Uint128,
 }
 
 pub static CONFIG: Item<Config> = Item::new(b"config");
 
+pub const DEFAULT_INSTANT_UNBOND_PENALTY: u32 = 500;
+pub const DEFAULT_GAUGE_EPOCH_SECONDS: u64 = 7 * 86400;
+pub const DEFAULT_REWARD_STREAM_SECONDS: u64 = 86400;
+pub const DEFAULT_KEEPER_BOUNTY: u32 = 10;
+pub const DEFAULT_MIN_UPKEEP_INTERVAL: u64 = 3600;
+pub const DEFAULT_VOLUME_BUCKET_SECONDS: u64 = 86400;
+pub const DEFAULT_VOLUME_WINDOW: u32 = 7;
+pub const MAX_VOLUME_WINDOW: u32 = 90;
+pub const DEFAULT_EARLY_VESTING_PENALTY: u32 = 5000;
+
+// 1, 3, 6 and 12 months
+pub fn default_lock_tiers() -> Vec<LockTier> {
+    const MONTH: u64 = 30 * 86400;
+    vec![
+        LockTier { duration: MONTH, multiplier: Uint128::new(11_000) },
+        LockTier { duration: 3 * MONTH, multiplier: Uint128::new(12_500) },
+        LockTier { duration: 6 * MONTH, multiplier: Uint128::new(15_000) },
+        LockTier { duration: 12 * MONTH, multiplier: Uint128::new(20_000) },
+    ]
+}
+
+// Allocation #1 from the staking contract
+pub fn default_allocation_sources(addrs: &ContractAddresses) -> Vec<AllocationSource> {
+    vec![AllocationSource {
+        allocation_id: 1,
+        contract: addrs.staking.clone(),
+    }]
+}
+
 // Minimal registry types
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -0,0 +1,71 @@
+use cosmwasm_std::{Addr, Uint128, Deps, StdResult, to_binary, QueryRequest, WasmQuery};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use secret_toolkit_storage::Keymap;
+
+use crate::state::ContractInfo;
+
+
+// A pool and the share of a voter's power given to it, in basis points
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct GaugeVote {
+    pub pool: Addr,
+    pub weight: Uint128,
+}
+
+// A user's latest vote. Power is the ERTH staked when the vote was cast; it is
+// capped at the voter's stake again when the epoch is finalized.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct GaugeVoteRecord {
+    pub epoch: u64,
+    pub power: Uint128,
+    pub votes: Vec<GaugeVote>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct GaugeWeight {
+    pub pool: Addr,
+    pub votes: Uint128,
+}
+
+pub static GAUGE_VOTES: Keymap<Addr, GaugeVoteRecord> = Keymap::new(b"gauge_votes");
+
+// Votes for an epoch by voter, suffixed with the epoch they apply to. Kept
+// until the epoch is finalized and each voter's power re-checked.
+pub static GAUGE_EPOCH_VOTES: Keymap<Addr, GaugeVoteRecord> = Keymap::new(b"gauge_epoch_votes");
+
+// Vote totals keyed by the epoch they apply to (the one after they were cast).
+// A running tally until the epoch is finalized with FinalizeGaugeEpoch.
+pub static GAUGE_WEIGHTS: Keymap<u64, Vec<GaugeWeight>> = Keymap::new(b"gauge_weights");
+
+// Re-checked totals for an epoch whose finalization is still in progress
+pub static GAUGE_VERIFIED_WEIGHTS: Keymap<u64, Vec<GaugeWeight>> = Keymap::new(b"gauge_verified_weights");
+
+// Epochs whose weights have been finalized. Upkeep ignores votes until then.
+pub static GAUGE_FINALIZED: Keymap<u64, bool> = Keymap::new(b"gauge_finalized");
+
+// Minimal staking contract types, only the fields read here
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StakingQueryMsg {
+    GetUserInfo { address: String },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StakerInfo {
+    pub staked_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StakingUserInfoResponse {
+    pub user_info: Option<StakerInfo>,
+}
+
+pub fn query_staked_amount(deps: &Deps, staking: &ContractInfo, user: &Addr) -> StdResult<Uint128> {
+    let response: StakingUserInfoResponse = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: staking.address.to_string(),
+        code_hash: staking.code_hash.clone(),
+        msg: to_binary(&StakingQueryMsg::GetUserInfo { address: user.to_string() })?,
+    }))?;
+    Ok(response.user_info.map(|info| info.staked_amount).unwrap_or_default())
+}
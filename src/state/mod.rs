@@ -2,13 +2,25 @@
 
 pub mod config;
 pub mod pool;
+pub mod gauge;
+pub mod vesting;
 
-pub use config::{Config, CONFIG, ContractInfo, ContractAddresses, query_registry, load_contracts};
-pub use pool::{PoolInfo, POOL_INFO, UserInfo, USER_INFO, PoolConfig, PENDING_POOL, PoolState,
-    UNBONDING_REQUESTS, UnbondRecord,
+pub use config::{Config, CONFIG, DEFAULT_INSTANT_UNBOND_PENALTY, DEFAULT_GAUGE_EPOCH_SECONDS, DEFAULT_REWARD_STREAM_SECONDS,
+    DEFAULT_KEEPER_BOUNTY, DEFAULT_MIN_UPKEEP_INTERVAL,
+    DEFAULT_VOLUME_BUCKET_SECONDS, DEFAULT_VOLUME_WINDOW, MAX_VOLUME_WINDOW,
+    DEFAULT_EARLY_VESTING_PENALTY, LockTier, default_lock_tiers, AllocationSource, default_allocation_sources, ContractInfo, ContractAddresses, query_registry, load_contracts};
+pub use vesting::{VestingEntry, REWARD_VESTING};
+pub use gauge::{GaugeVote, GaugeVoteRecord, GaugeWeight, GAUGE_VOTES, GAUGE_EPOCH_VOTES, GAUGE_WEIGHTS,
+    GAUGE_VERIFIED_WEIGHTS, GAUGE_FINALIZED, query_staked_amount};
+pub use pool::{PoolInfo, POOL_INFO, UserInfo, StakeLock, USER_INFO, PoolIncentive, IncentiveStream,
+    BoostExpiry, RewardCheckpoint,
+    UserIncentive, INCENTIVE_TOKENS, PoolConfig, PENDING_POOL, PendingPool, PoolState,
+    POOL_MIGRATION, PoolMigration, PoolMigrationStage,
+    UNBONDING_REQUESTS, UnbondRecord, LateClaimPolicy, AUTO_CLAIM_UNBOND, AUTO_COMPOUND,
+    USER_POOLS, track_user_pool, prune_user_pools,
 };
 
-use cosmwasm_std::{Uint128};
+use cosmwasm_std::{Addr, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use secret_toolkit_storage::{Item};
@@ -18,6 +30,27 @@ pub struct State {
     pub erth_burned: Uint128,
     pub anml_burned: Uint128,
     pub pending_reward: Uint128,
+    // ERTH received from each allocation source
+    pub allocations_received: Vec<AllocationReceived>,
+    // when UpdatePoolRewards was last called
+    pub last_upkeep: u64,
+    // rewards held back by upkeep because no pool could take them
+    pub undistributed_rewards: Uint128,
+    // total sent to the undistributed_sink
+    pub undistributed_sent: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct AllocationReceived {
+    pub allocation_id: u32,
+    pub amount: Uint128,
 }
 
 pub static STATE: Item<State> = Item::new(b"state");
+
+// Caller of the UpdatePoolRewards waiting on its reply, paid the keeper bounty
+pub static PENDING_UPKEEP_CALLER: Item<Addr> = Item::new(b"pending_upkeep_caller");
+
+// Bumped whenever stored layouts change; checked by MigrateMsg::Upgrade
+pub const CURRENT_STORAGE_VERSION: u32 = 2;
+pub static STORAGE_VERSION: Item<u32> = Item::new(b"storage_version");
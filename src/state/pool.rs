@@ -1,19 +1,118 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Uint128, Uint256, StdResult, Storage};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use secret_toolkit_storage::{Item, Keymap};
 
+use crate::state::ContractInfo;
+
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct PoolState {
     pub total_shares: Uint128,
-    pub reward_per_token_scaled: Uint128,
+    pub reward_per_token_scaled: Uint256,
     pub erth_reserve: Uint128,
     pub token_b_reserve: Uint128,
-    pub daily_rewards: [Uint128; 7],
-    pub daily_volumes: [Uint128; 7],
-    pub last_updated_day: u64,
+    // rewards and swap volume per bucket, newest first
+    pub reward_history: Vec<Uint128>,
+    pub volume_history: Vec<Uint128>,
+    // start time of the newest bucket
+    pub history_start: u64,
     pub unbonding_shares: Uint128,
+    // shares held in user stakes, the only ones that earn rewards
+    pub staked_shares: Uint128,
+    // shares minted out as LP tokens; these are not staked
+    pub lp_token_supply: Uint128,
+    // extra effective shares from time-locked stakes
+    pub boosted_shares: Uint128,
+    // when lock boosts stop earning, soonest first
+    pub boost_expiries: Vec<BoostExpiry>,
+    // winding down: no swaps, deposits or rewards, and withdrawals skip unbonding
+    pub deprecated: bool,
+    // external reward tokens, each with its own accumulator
+    pub incentives: Vec<PoolIncentive>,
+    // ERTH per second (scaled by SCALING_FACTOR) streamed into
+    // reward_per_token_scaled until period_finish
+    pub reward_rate: Uint256,
+    pub period_finish: u64,
+    pub last_reward_update: u64,
+    // ERTH streamed while nothing was staked, handed back to the contract's
+    // undistributed rewards at the next upkeep
+    pub undistributed_rewards: Uint128,
+}
+
+impl PoolState {
+    // Adds swap volume to the newest bucket
+    pub fn record_volume(&mut self, amount: Uint128) {
+        match self.volume_history.first_mut() {
+            Some(bucket) => *bucket += amount,
+            None => self.volume_history.push(amount),
+        }
+    }
+
+    // Starts a fresh bucket for every bucket_seconds passed since history_start
+    // and trims both histories to `window` buckets
+    pub fn roll_history(&mut self, now: u64, bucket_seconds: u64, window: u32) {
+        let bucket_start = now - now % bucket_seconds;
+        if bucket_start > self.history_start {
+            let passed = (bucket_start - self.history_start).div_ceil(bucket_seconds);
+            let fresh = passed.min(window as u64) as usize;
+            for history in [&mut self.reward_history, &mut self.volume_history] {
+                history.splice(0..0, std::iter::repeat_n(Uint128::zero(), fresh));
+            }
+            self.history_start = bucket_start;
+        }
+        for history in [&mut self.reward_history, &mut self.volume_history] {
+            history.resize(window as usize, Uint128::zero());
+        }
+    }
+
+    // Volume over the window, each bucket weighted `decay` basis points of the newer one
+    pub fn weighted_volume(&self, decay: Uint128) -> Uint128 {
+        let mut weight = Uint128::from(10000u32);
+        let mut total = Uint128::zero();
+        for volume in self.volume_history.iter() {
+            total += volume.multiply_ratio(weight, 10000u32);
+            weight = weight.multiply_ratio(decay, 10000u32);
+        }
+        total
+    }
+}
+
+// Lock boosts that stop earning at `time`. Once it passes, the accumulators
+// are recorded so each lock's boost is settled up to then and no further.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct BoostExpiry {
+    pub time: u64,
+    pub boost_shares: Uint128,
+    // locks whose boost ends here and hasn't been settled yet
+    pub locks: u32,
+    pub checkpoint: Option<RewardCheckpoint>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct RewardCheckpoint {
+    pub reward_per_token_scaled: Uint256,
+    // each incentive token's accumulator
+    pub incentives: Vec<(Addr, Uint256)>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct PoolIncentive {
+    pub token: ContractInfo,
+    pub reward_per_token_scaled: Uint256,
+    pub last_update: u64,
+    pub streams: Vec<IncentiveStream>,
+    // streamed while nothing was staked; added to the token's next stream,
+    // or returned to the contract manager when the pool is removed
+    pub undistributed: Uint128,
+}
+
+// Amount paid out linearly between start_time and end_time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct IncentiveStream {
+    pub amount: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -21,6 +120,22 @@ pub struct PoolConfig {
     pub token_b_contract: Addr,
     pub token_b_hash: String,
     pub token_b_symbol: String,
+    pub lp_token: Option<ContractInfo>,
+    // overrides for the global unbonding settings in Config
+    pub unbonding_seconds: Option<u64>,
+    pub unbonding_window: Option<u64>,
+    pub late_claim_policy: LateClaimPolicy,
+}
+
+// What happens to an unbond request that is not claimed within its window
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LateClaimPolicy {
+    AutoRestake,
+    // claimable any time after the window, minus a penalty in basis points
+    LatePenalty { penalty: Uint128 },
+    // one more window of the same length, then auto-restake
+    ExtendOnce,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
@@ -35,13 +150,70 @@ pub static POOL_INFO: Keymap<Addr, PoolInfo> = Keymap::new(b"pool_info");
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
 pub struct UserInfo {
     pub amount_staked: Uint128,
-    pub reward_debt: Uint128,
+    // scaled by SCALING_FACTOR, like the accumulator it tracks
+    pub reward_debt: Uint256,
+    pub pending_rewards: Uint128,
+    pub locks: Vec<StakeLock>,
+    pub incentives: Vec<UserIncentive>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct UserIncentive {
+    pub token: Addr,
+    // scaled by SCALING_FACTOR, like the accumulator it tracks
+    pub reward_debt: Uint256,
     pub pending_rewards: Uint128,
 }
 
+// Staked shares that can't be withdrawn before unlock_time, earning rewards
+// on amount + boost_shares. The boost ends at the start of the day the lock
+// does and is zeroed once settled.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct StakeLock {
+    pub amount: Uint128,
+    pub boost_shares: Uint128,
+    pub unlock_time: u64,
+}
+
 pub static USER_INFO: Keymap<Addr, UserInfo> = Keymap::new(b"user_info");
 
-pub static PENDING_POOL: Item<Addr> = Item::new(b"pending_pool");
+// Pool waiting on its LP token instantiate reply
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PendingPool {
+    pub pool: Addr,
+    pub lp_token_hash: String,
+}
+
+pub static PENDING_POOL: Item<PendingPool> = Item::new(b"pending_pool");
+
+// Which records a pool token migration is moving
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum PoolMigrationStage {
+    Stakers,
+    Unbonding,
+    GaugeVotes,
+    EpochVotes(u64),
+}
+
+// A pool token migration in progress. The pool is taken out of POOL_INFO and
+// held here until every record has moved, so nothing can touch it meanwhile.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PoolMigration {
+    pub old_pool: Addr,
+    pub new_pool: Addr,
+    pub new_hash: String,
+    pub pool_info: PoolInfo,
+    // gauge epoch when the migration started
+    pub epoch: u64,
+    pub stage: PoolMigrationStage,
+    // position in the gauge vote records being renamed
+    pub cursor: u32,
+    pub users_migrated: u32,
+    // the contract's key on the new token, for the final balance check
+    pub viewing_key: String,
+}
+
+pub static POOL_MIGRATION: Item<PoolMigration> = Item::new(b"pool_migration");
 
 
 // For demonstration, let's define a record we want to store:
@@ -50,9 +222,57 @@ pub struct UnbondRecord {
     pub pool: Addr,
     pub amount: Uint128,
     pub start_time: u64,
+    // unbonding settings in effect when the request was made
+    pub unbonding_seconds: u64,
+    pub unbonding_window: u64,
+    pub late_claim_policy: LateClaimPolicy,
 }
 
 // Base KeyMap. We'll build "sub-keys" for each pool by concatenating prefixes at runtime.
 pub static UNBONDING_REQUESTS: Keymap<Addr, Vec<UnbondRecord>> = Keymap::new(b"unbonding_requests");
 
+// Users who let anyone trigger their unbond payouts at maturity
+pub static AUTO_CLAIM_UNBOND: Keymap<Addr, bool> = Keymap::new(b"auto_claim_unbond");
+
+// SNIP-20s this contract is registered with to receive incentives, and their hashes
+pub static INCENTIVE_TOKENS: Keymap<Addr, String> = Keymap::new(b"incentive_tokens");
 
+// The keeper each user lets compound their rewards into their stake. The zap
+// trades at the pool price, so only a keeper the user trusts may trigger it.
+pub static AUTO_COMPOUND: Keymap<Addr, Addr> = Keymap::new(b"auto_compound");
+
+// Pools each user has staked shares or unbond requests in
+pub static USER_POOLS: Keymap<Addr, Vec<Addr>> = Keymap::new(b"user_pools");
+
+pub fn track_user_pool(storage: &mut dyn Storage, user: &Addr, pool: &Addr) -> StdResult<()> {
+    let mut pools = USER_POOLS.get(storage, user).unwrap_or_default();
+    if !pools.contains(pool) {
+        pools.push(pool.clone());
+        USER_POOLS.insert(storage, user, &pools)?;
+    }
+    Ok(())
+}
+
+// Drops pools where the user no longer has a stake or unbond requests
+pub fn prune_user_pools(storage: &mut dyn Storage, user: &Addr) -> StdResult<()> {
+    let pools = USER_POOLS.get(storage, user).unwrap_or_default();
+    let remaining: Vec<Addr> = pools
+        .into_iter()
+        .filter(|pool| {
+            let has_stake = USER_INFO.add_suffix(pool.as_bytes()).contains(storage, user);
+            let has_unbonding = !UNBONDING_REQUESTS
+                .add_suffix(pool.as_bytes())
+                .get(storage, user)
+                .unwrap_or_default()
+                .is_empty();
+            has_stake || has_unbonding
+        })
+        .collect();
+
+    if remaining.is_empty() {
+        USER_POOLS.remove(storage, user)?;
+    } else {
+        USER_POOLS.insert(storage, user, &remaining)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use cosmwasm_std::{Addr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use secret_toolkit_storage::Keymap;
+
+
+// Claimed ERTH released linearly between start_time and end_time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VestingEntry {
+    pub amount: Uint128,
+    pub claimed: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+impl VestingEntry {
+    // Amount released by `now`, including what was already claimed
+    pub fn vested(&self, now: u64) -> Uint128 {
+        if now >= self.end_time {
+            self.amount
+        } else if now <= self.start_time {
+            Uint128::zero()
+        } else {
+            self.amount.multiply_ratio(now - self.start_time, self.end_time - self.start_time)
+        }
+    }
+}
+
+// Each user's vesting rewards, oldest first
+pub static REWARD_VESTING: Keymap<Addr, Vec<VestingEntry>> = Keymap::new(b"reward_vesting");